@@ -0,0 +1,39 @@
+#import bevy_sprite::mesh2d_vertex_output::VertexOutput
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(0)
+var texture: texture_2d<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(1)
+var texture_sampler: sampler;
+// x: intensity, y: time, z: inversion, w: unused (webgl2 wants 16 byte uniforms)
+@group(#{MATERIAL_BIND_GROUP}) @binding(2)
+var<uniform> params: vec4<f32>;
+
+fn hash(n: f32) -> f32 {
+    return fract(sin(n) * 43758.5453);
+}
+
+@fragment
+fn fragment(mesh: VertexOutput) -> @location(0) vec4<f32> {
+    let uv = mesh.uv;
+    let intensity = params.x;
+    let time = params.y;
+
+    // whole rows jump sideways for a few frames, select keeps the samples below in uniform control flow
+    let row = floor(uv.y * 40.0);
+    let jump = hash(row + floor(time * 15.0)) > 1.0 - intensity * 0.3;
+    let shift = select(0.0, (hash(row * 7.0 + time) - 0.5) * 0.1 * intensity, jump);
+    let shifted = vec2<f32>(uv.x + shift, uv.y);
+
+    let offset = vec2<f32>(0.01 * intensity, 0.0);
+    var colour = vec3<f32>(
+        textureSample(texture, texture_sampler, shifted + offset).r,
+        textureSample(texture, texture_sampler, shifted).g,
+        textureSample(texture, texture_sampler, shifted - offset).b
+    );
+
+    let scanline = 0.5 + 0.5 * sin(mesh.position.y * 3.14159);
+    colour = colour * (1.0 - intensity * 0.4 * scanline);
+    colour = mix(colour, vec3<f32>(1.0) - colour, params.z);
+
+    return vec4<f32>(colour, 1.0);
+}
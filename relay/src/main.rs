@@ -0,0 +1,243 @@
+// headless relay for online haskellHop, it only keeps lobbies and forwards lines between their members
+//
+//   client -> relay: HOST <mode> <assists> | JOIN <code> | INPUT <frame> <bits>
+//   relay -> client: CODE <code> | START <slot> <mode> <seed> <assists> | INPUT <slot> <frame> <bits> | LEFT <slot> | ERROR <message>
+//
+// error messages are english text the clients look up in their string tables
+
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use rand::{thread_rng, Rng};
+use tungstenite::{accept, Message};
+
+const DEFAULT_ADDR: &str = "0.0.0.0:9001";
+const CODE_LEN: usize = 4;
+const MAX_PLAYERS: usize = 2;
+const POLL_MS: u64 = 1;
+
+struct Lobby {
+  mode: String,
+  // the host's movement assists, passed on as they came so every client plays with them
+  assists: String,
+  seed: u64,
+  member_vec: Vec<(usize, Sender<String>)>,
+}
+
+#[derive(Default)]
+struct Lobbies {
+  lobby_map: HashMap<String, Lobby>,
+}
+
+impl Lobbies {
+  fn host(&mut self, mode: &str, assists: &str, sender: Sender<String>) -> String {
+    let mut rng = thread_rng();
+    let code = loop {
+      let code = (0..CODE_LEN)
+        .map(|_| rng.gen_range(b'A'..=b'Z') as char)
+        .collect::<String>();
+      if !self.lobby_map.contains_key(&code) {
+        break code;
+      }
+    };
+
+    self.lobby_map.insert(
+      code.clone(),
+      Lobby {
+        mode: mode.to_string(),
+        assists: assists.to_string(),
+        seed: rng.gen(),
+        member_vec: vec![(0, sender)],
+      },
+    );
+    code
+  }
+
+  fn join(&mut self, code: &str, sender: Sender<String>) -> Result<usize, &'static str> {
+    let lobby = self.lobby_map.get_mut(code).ok_or("no such lobby")?;
+    if lobby.member_vec.len() >= MAX_PLAYERS {
+      return Err("lobby is full");
+    }
+
+    let slot = (0..MAX_PLAYERS)
+      .find(|slot| lobby.member_vec.iter().all(|(s, _)| s != slot))
+      .unwrap();
+    lobby.member_vec.push((slot, sender));
+
+    // everyone starts together once the lobby fills up
+    if lobby.member_vec.len() == MAX_PLAYERS {
+      for (slot, sender) in &lobby.member_vec {
+        let _ = sender.send(format!(
+          "START {} {} {} {}",
+          slot, lobby.mode, lobby.seed, lobby.assists
+        ));
+      }
+    }
+    Ok(slot)
+  }
+
+  fn broadcast(&self, code: &str, from: usize, line: &str) {
+    if let Some(lobby) = self.lobby_map.get(code) {
+      for (slot, sender) in &lobby.member_vec {
+        if *slot != from {
+          let _ = sender.send(line.to_string());
+        }
+      }
+    }
+  }
+
+  fn leave(&mut self, code: &str, slot: usize) {
+    self.broadcast(code, slot, &format!("LEFT {}", slot));
+    if let Some(lobby) = self.lobby_map.get_mut(code) {
+      lobby.member_vec.retain(|(s, _)| *s != slot);
+      if lobby.member_vec.is_empty() {
+        self.lobby_map.remove(code);
+      }
+    }
+  }
+}
+
+fn handle(stream: TcpStream, lobbies: Arc<Mutex<Lobbies>>) -> anyhow::Result<()> {
+  let mut socket = accept(stream).map_err(|e| anyhow!("handshake failed: {}", e))?;
+  socket.get_mut().set_nonblocking(true)?;
+
+  let (sender, receiver): (Sender<String>, Receiver<String>) = channel();
+  let mut joined: Option<(String, usize)> = None;
+
+  let result = loop {
+    // a nonblocking socket queues what it can't send yet and flushes it on the next read
+    for line in receiver.try_iter() {
+      match socket.write_message(Message::Text(line)) {
+        Err(tungstenite::Error::Io(e)) if e.kind() == ErrorKind::WouldBlock => {}
+        result => result?,
+      }
+    }
+
+    let line = match socket.read_message() {
+      Ok(Message::Text(line)) => line,
+      Ok(Message::Close(_)) => break Ok(()),
+      Ok(_) => continue,
+      Err(tungstenite::Error::Io(e)) if e.kind() == ErrorKind::WouldBlock => {
+        thread::sleep(Duration::from_millis(POLL_MS));
+        continue;
+      }
+      Err(e) => break Err(e.into()),
+    };
+
+    let mut words = line.split_whitespace();
+    let mut lobbies = lobbies.lock().unwrap();
+    match (words.next(), &joined) {
+      (Some("HOST"), None) => {
+        let mode = words.next().unwrap_or("coop");
+        let code = lobbies.host(mode, words.next().unwrap_or(""), sender.clone());
+        sender.send(format!("CODE {}", code)).unwrap();
+        joined = Some((code, 0));
+      }
+      (Some("JOIN"), None) => {
+        let code = words.next().unwrap_or("").to_uppercase();
+        match lobbies.join(&code, sender.clone()) {
+          Ok(slot) => joined = Some((code, slot)),
+          Err(message) => sender.send(format!("ERROR {}", message)).unwrap(),
+        }
+      }
+      (Some("INPUT"), Some((code, slot))) => {
+        let rest = words.collect::<Vec<_>>().join(" ");
+        lobbies.broadcast(code, *slot, &format!("INPUT {} {}", slot, rest));
+      }
+      // clients translate the message, so what came in goes to the log instead
+      _ => {
+        eprintln!("bad message {:?}", line);
+        sender.send("ERROR bad message".to_string()).unwrap()
+      }
+    }
+  };
+
+  if let Some((code, slot)) = joined {
+    lobbies.lock().unwrap().leave(&code, slot);
+  }
+  result
+}
+
+fn main() -> std::io::Result<()> {
+  let addr = std::env::args()
+    .nth(1)
+    .unwrap_or_else(|| DEFAULT_ADDR.to_string());
+  let listener = TcpListener::bind(&addr)?;
+  let lobbies = Arc::new(Mutex::new(Lobbies::default()));
+  println!("relay listening on {}", addr);
+
+  for stream in listener.incoming() {
+    let stream = match stream {
+      Ok(stream) => stream,
+      Err(e) => {
+        eprintln!("accept failed: {}", e);
+        continue;
+      }
+    };
+    let lobbies = lobbies.clone();
+    thread::spawn(move || {
+      let peer = stream.peer_addr().ok();
+      if let Err(e) = handle(stream, lobbies) {
+        eprintln!("{:?} dropped: {}", peer, e);
+      }
+    });
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn lobby_starts_when_full() {
+    let mut lobbies = Lobbies::default();
+    let (host, host_lines) = channel();
+    let code = lobbies.host("versus", "6", host);
+    assert_eq!(code.len(), CODE_LEN);
+    assert!(host_lines.try_recv().is_err());
+
+    let (guest, guest_lines) = channel();
+    assert_eq!(lobbies.join(&code, guest), Ok(1));
+    let seed = lobbies.lobby_map[&code].seed;
+    assert_eq!(
+      host_lines.try_recv().unwrap(),
+      format!("START 0 versus {} 6", seed)
+    );
+    assert_eq!(
+      guest_lines.try_recv().unwrap(),
+      format!("START 1 versus {} 6", seed)
+    );
+
+    let (late, _) = channel();
+    assert_eq!(lobbies.join(&code, late), Err("lobby is full"));
+    let (lost, _) = channel();
+    assert_eq!(lobbies.join("ZZZZZ", lost), Err("no such lobby"));
+  }
+
+  #[test]
+  fn inputs_and_leaving_reach_the_other_member() {
+    let mut lobbies = Lobbies::default();
+    let (host, host_lines) = channel();
+    let code = lobbies.host("coop", "", host);
+    let (guest, guest_lines) = channel();
+    lobbies.join(&code, guest).unwrap();
+    host_lines.try_recv().unwrap();
+    guest_lines.try_recv().unwrap();
+
+    lobbies.broadcast(&code, 0, "INPUT 0 4 2");
+    assert_eq!(guest_lines.try_recv().unwrap(), "INPUT 0 4 2");
+    assert!(host_lines.try_recv().is_err());
+
+    lobbies.leave(&code, 1);
+    assert_eq!(host_lines.try_recv().unwrap(), "LEFT 1");
+    lobbies.leave(&code, 0);
+    assert!(lobbies.lobby_map.is_empty());
+  }
+}
@@ -0,0 +1,220 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::locale::Locale;
+use crate::menu::{spawn_menu, update_menu};
+use crate::saves;
+
+const RECORD_PATH: &str = "saves/access.ron";
+const TEXT_SCALES: [f32; 4] = [1.0, 1.25, 1.5, 2.0];
+// a slow fade takes this many times as long to look finished
+pub const SLOW_FADE: f32 = 4.0;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum Fades {
+  NORMAL,
+  SLOW,
+  OFF,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum Palette {
+  DEFAULT,
+  HIGH_CONTRAST,
+  COLOUR_BLIND,
+}
+
+impl Palette {
+  // only greens and cyans move, they're the colours the corruption and the power-ups lean on
+  pub fn apply(self, colour: Color) -> Color {
+    let hsla = Hsla::from(colour);
+    if self == Palette::DEFAULT || hsla.saturation < 0.2 {
+      return colour;
+    }
+    let green = (75.0..165.0).contains(&hsla.hue);
+    let cyan = (165.0..200.0).contains(&hsla.hue);
+    let replacement = match (self, green, cyan) {
+      // okabe-ito yellow and sky blue, told apart by every common colour blindness
+      (Palette::COLOUR_BLIND, true, _) => Color::srgb(0.95, 0.9, 0.25),
+      (Palette::COLOUR_BLIND, _, true) => Color::srgb(0.35, 0.7, 0.9),
+      (Palette::HIGH_CONTRAST, true, _) => Color::srgb(1.0, 1.0, 0.0),
+      (Palette::HIGH_CONTRAST, _, true) => Color::WHITE,
+      _ => return colour,
+    };
+    replacement.with_alpha(colour.alpha())
+  }
+
+  fn name(self) -> &'static str {
+    match self {
+      Palette::DEFAULT => "default",
+      Palette::HIGH_CONTRAST => "high contrast",
+      Palette::COLOUR_BLIND => "colour blind",
+    }
+  }
+}
+
+// cosmetic only, the simulation never reads these so runs replay the same with any of them on
+#[derive(Resource, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct Access {
+  // no spinning score, text wobble, camera shake or background bob
+  pub reduced_motion: bool,
+  // no glyph flicker, glitch inverts or hurt blinking
+  pub reduce_flashing: bool,
+  pub fades: Fades,
+  pub palette: Palette,
+  pub text_scale: f32,
+  #[serde(skip)]
+  menu_open: bool,
+}
+
+impl Default for Access {
+  fn default() -> Access {
+    Access {
+      reduced_motion: false,
+      reduce_flashing: false,
+      fades: Fades::NORMAL,
+      palette: Palette::DEFAULT,
+      text_scale: 1.0,
+      menu_open: false,
+    }
+  }
+}
+
+impl Access {
+  fn menu_text(&self, locale: &Locale) -> String {
+    let on_off = |on: bool| locale.tr(if on { "on" } else { "off" }).to_string();
+    let fades = match self.fades {
+      Fades::NORMAL => "normal",
+      Fades::SLOW => "slow",
+      Fades::OFF => "off",
+    };
+    [
+      locale.tr("accessibility").to_string(),
+      format!(
+        "1 {}: {}",
+        locale.tr("reduced motion"),
+        on_off(self.reduced_motion)
+      ),
+      format!(
+        "2 {}: {}",
+        locale.tr("reduce flashing"),
+        on_off(self.reduce_flashing)
+      ),
+      format!("3 {}: {}", locale.tr("colour fades"), locale.tr(fades)),
+      format!(
+        "4 {}: {}",
+        locale.tr("palette"),
+        locale.tr(self.palette.name())
+      ),
+      format!(
+        "5 {}: {}%",
+        locale.tr("text size"),
+        (self.text_scale * 100.0).round()
+      ),
+      String::new(),
+      locale.tr("1-5 to change, F4 to close").to_string(),
+    ]
+    .join("\n")
+  }
+}
+
+#[derive(Component)]
+pub struct AccessMenu;
+
+#[derive(Component)]
+pub struct AccessMenuText;
+
+pub fn access_setup(
+  mut access: ResMut<Access>,
+  mut commands: Commands,
+  asset_server: Res<AssetServer>,
+) {
+  *access = saves::load(RECORD_PATH).unwrap_or_default();
+
+  spawn_menu(&mut commands, &asset_server, AccessMenu, AccessMenuText);
+}
+
+pub fn access_update(
+  keyboard_input: Res<ButtonInput<KeyCode>>,
+  locale: Res<Locale>,
+  mut access: ResMut<Access>,
+  mut ui_scale: ResMut<UiScale>,
+  mut menu_q: Query<&mut Visibility, With<AccessMenu>>,
+  mut menu_text_q: Query<&mut Text, With<AccessMenuText>>,
+) {
+  if keyboard_input.just_pressed(KeyCode::F4) {
+    access.menu_open = !access.menu_open;
+  }
+  if access.menu_open {
+    let before = access.clone();
+    if keyboard_input.just_pressed(KeyCode::Digit1) {
+      access.reduced_motion = !access.reduced_motion;
+    }
+    if keyboard_input.just_pressed(KeyCode::Digit2) {
+      access.reduce_flashing = !access.reduce_flashing;
+    }
+    if keyboard_input.just_pressed(KeyCode::Digit3) {
+      access.fades = match access.fades {
+        Fades::NORMAL => Fades::SLOW,
+        Fades::SLOW => Fades::OFF,
+        Fades::OFF => Fades::NORMAL,
+      };
+    }
+    if keyboard_input.just_pressed(KeyCode::Digit4) {
+      access.palette = match access.palette {
+        Palette::DEFAULT => Palette::HIGH_CONTRAST,
+        Palette::HIGH_CONTRAST => Palette::COLOUR_BLIND,
+        Palette::COLOUR_BLIND => Palette::DEFAULT,
+      };
+    }
+    if keyboard_input.just_pressed(KeyCode::Digit5) {
+      let next = TEXT_SCALES
+        .iter()
+        .position(|scale| *scale == access.text_scale)
+        .map_or(0, |k| (k + 1) % TEXT_SCALES.len());
+      access.text_scale = TEXT_SCALES[next];
+    }
+    if *access != before {
+      saves::save(RECORD_PATH, &*access);
+    }
+  }
+  update_menu(access.menu_open, &mut menu_q, &mut menu_text_q, || {
+    access.menu_text(&locale)
+  });
+
+  // the hud and menus scale through the ui, world text picks text_scale up in text_fx_update
+  if ui_scale.0 != access.text_scale {
+    ui_scale.0 = access.text_scale;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn palettes_only_move_greens_and_cyans() {
+    let lime = Color::srgba(0.0, 1.0, 0.0, 0.5);
+    let cyan = Color::srgb(0.0, 0.8, 1.0);
+    let red = Color::srgb(1.0, 0.2, 0.2);
+    let grey = Color::srgb(0.4, 0.5, 0.4);
+
+    for colour in [lime, cyan, red, grey] {
+      assert_eq!(Palette::DEFAULT.apply(colour), colour);
+    }
+    for palette in [Palette::HIGH_CONTRAST, Palette::COLOUR_BLIND] {
+      assert_eq!(palette.apply(red), red);
+      assert_eq!(palette.apply(grey), grey);
+      assert_ne!(palette.apply(lime), lime);
+      assert_ne!(palette.apply(cyan), cyan);
+      // the fades lean on alpha, so it carries over
+      assert_eq!(palette.apply(lime).alpha(), 0.5);
+    }
+    assert_eq!(Palette::HIGH_CONTRAST.apply(cyan), Color::WHITE);
+    assert_eq!(
+      Palette::COLOUR_BLIND.apply(lime),
+      Color::srgba(0.95, 0.9, 0.25, 0.5)
+    );
+  }
+}
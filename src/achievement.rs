@@ -0,0 +1,283 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::bot::Bot;
+use crate::local::Local;
+use crate::locale::Locale;
+use crate::menu::{spawn_menu, update_menu};
+use crate::saves;
+use crate::text_fx::{TextEffect, TextFx};
+use crate::{GameEvent, Player};
+
+const RECORD_PATH: &str = "saves/achievements.ron";
+const FUNNY_JUMP: usize = 69;
+const DEFLECT_GOAL: usize = 100;
+const AIRBORNE_MS: usize = 10000;
+const TOAST_MS: usize = 4000;
+const TOAST_FONT_SIZE: f32 = 20.0;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Achievement {
+  FUNNY_NUMBER,
+  CORRUPTED,
+  SURVIVOR,
+  DEFLECTOR,
+  AIRBORNE,
+}
+
+// menu order
+const ALL: [Achievement; 5] = [
+  Achievement::FUNNY_NUMBER,
+  Achievement::CORRUPTED,
+  Achievement::SURVIVOR,
+  Achievement::DEFLECTOR,
+  Achievement::AIRBORNE,
+];
+
+impl Achievement {
+  pub fn name(self) -> &'static str {
+    match self {
+      Achievement::FUNNY_NUMBER => "haha funny number",
+      Achievement::CORRUPTED => "corrupted",
+      Achievement::SURVIVOR => "it's just java",
+      Achievement::DEFLECTOR => "an apple a day",
+      Achievement::AIRBORNE => "the floor is lava",
+    }
+  }
+
+  pub fn description(self, locale: &Locale) -> String {
+    match self {
+      Achievement::FUNNY_NUMBER => locale.format("reach {} jumps", &[&FUNNY_JUMP]),
+      Achievement::CORRUPTED => locale.tr("reach the corruption").to_string(),
+      Achievement::SURVIVOR => locale.tr("survive java's entrance").to_string(),
+      Achievement::DEFLECTOR => locale.format("deflect {} apples", &[&DEFLECT_GOAL]),
+      Achievement::AIRBORNE => {
+        locale.format("stay off the floor for {}s", &[&(AIRBORNE_MS / 1000)])
+      }
+    }
+  }
+}
+
+// deflections add up across runs
+#[derive(Serialize, Deserialize, Default)]
+struct Record {
+  unlocked: Vec<Achievement>,
+  deflections: usize,
+}
+
+#[derive(Resource, Default)]
+pub struct Achievements {
+  record: Record,
+  last_jump_count: usize,
+  airborne_since: Option<usize>,
+  // unlocked since the last frame, waiting for a toast
+  pending: Vec<Achievement>,
+  menu_open: bool,
+}
+
+impl Achievements {
+  pub fn is_unlocked(&self, achievement: Achievement) -> bool {
+    self.record.unlocked.contains(&achievement)
+  }
+
+  fn unlock(&mut self, achievement: Achievement) {
+    if !self.is_unlocked(achievement) {
+      self.record.unlocked.push(achievement);
+      self.pending.push(achievement);
+      saves::save(RECORD_PATH, &self.record);
+    }
+  }
+
+  fn menu_text(&self, locale: &Locale) -> String {
+    let mut lines = vec![locale.format(
+      "achievements {}/{}",
+      &[&self.record.unlocked.len(), &ALL.len()],
+    )];
+    for achievement in ALL {
+      let mark = if self.is_unlocked(achievement) {
+        "x"
+      } else {
+        " "
+      };
+      let progress = match achievement {
+        Achievement::DEFLECTOR if !self.is_unlocked(achievement) => {
+          format!(" ({}/{})", self.record.deflections, DEFLECT_GOAL)
+        }
+        _ => String::new(),
+      };
+      lines.push(format!(
+        "[{}] {} - {}{}",
+        mark,
+        locale.tr(achievement.name()),
+        achievement.description(locale),
+        progress
+      ));
+    }
+    lines.push(String::new());
+    lines.push(locale.tr("F6 to close").to_string());
+    lines.join("\n")
+  }
+}
+
+#[derive(Component)]
+pub struct ToastColumn;
+
+#[derive(Component)]
+pub struct Toast {
+  time_save: usize,
+}
+
+#[derive(Component)]
+pub struct AchievementMenu;
+
+#[derive(Component)]
+pub struct AchievementMenuText;
+
+pub fn achievement_setup(
+  mut achievements: ResMut<Achievements>,
+  mut commands: Commands,
+  asset_server: Res<AssetServer>,
+) {
+  achievements.record = saves::load(RECORD_PATH).unwrap_or_default();
+
+  // toasts stack upwards from the bottom right corner
+  commands.spawn((
+    Node {
+      position_type: PositionType::Absolute,
+      right: Val::Px(10.0),
+      bottom: Val::Px(10.0),
+      flex_direction: FlexDirection::ColumnReverse,
+      align_items: AlignItems::FlexEnd,
+      row_gap: Val::Px(6.0),
+      ..default()
+    },
+    ToastColumn,
+  ));
+
+  spawn_menu(
+    &mut commands,
+    &asset_server,
+    AchievementMenu,
+    AchievementMenuText,
+  );
+}
+
+// demo runs don't count, everything else plays for player one
+pub fn achievement_update(
+  time: Res<Time>,
+  player: Res<Player>,
+  local: Res<Local>,
+  bot: Res<Bot>,
+  mut achievements: ResMut<Achievements>,
+  mut events: MessageReader<GameEvent>,
+) {
+  let events = events.read().copied().collect::<Vec<_>>();
+  let tc = time.elapsed().as_millis() as usize;
+  let jumps = local.team_jumps(&player);
+  let last = std::mem::replace(&mut achievements.last_jump_count, jumps);
+
+  if bot.active {
+    achievements.airborne_since = None;
+    return;
+  }
+
+  // co-op can go from 68 to 70 in one tick
+  if last < FUNNY_JUMP && jumps >= FUNNY_JUMP {
+    achievements.unlock(Achievement::FUNNY_NUMBER);
+  }
+  for event in events {
+    match event {
+      GameEvent::CORRUPT_A => achievements.unlock(Achievement::CORRUPTED),
+      GameEvent::JAVA_REVEALED => achievements.unlock(Achievement::SURVIVOR),
+      GameEvent::DEFLECT => {
+        achievements.record.deflections += 1;
+        if achievements.record.deflections >= DEFLECT_GOAL {
+          achievements.unlock(Achievement::DEFLECTOR);
+        } else {
+          saves::save(RECORD_PATH, &achievements.record);
+        }
+      }
+      _ => {}
+    }
+  }
+
+  // the clock starts when the player leaves the floor, a fresh run starts grounded
+  if player.grounded || player.jump_count == 0 {
+    achievements.airborne_since = None;
+  } else {
+    let since = *achievements.airborne_since.get_or_insert(tc);
+    if tc - since >= AIRBORNE_MS {
+      achievements.unlock(Achievement::AIRBORNE);
+    }
+  }
+}
+
+pub fn achievement_ui(
+  time: Res<Time>,
+  keyboard_input: Res<ButtonInput<KeyCode>>,
+  asset_server: Res<AssetServer>,
+  locale: Res<Locale>,
+  mut achievements: ResMut<Achievements>,
+  column_q: Query<Entity, With<ToastColumn>>,
+  toast_q: Query<(Entity, &Toast)>,
+  mut menu_q: Query<&mut Visibility, With<AchievementMenu>>,
+  mut menu_text_q: Query<&mut Text, With<AchievementMenuText>>,
+  mut commands: Commands,
+) {
+  let tc = time.elapsed().as_millis() as usize;
+
+  if keyboard_input.just_pressed(KeyCode::F6) {
+    achievements.menu_open = !achievements.menu_open;
+  }
+  update_menu(
+    achievements.menu_open,
+    &mut menu_q,
+    &mut menu_text_q,
+    || achievements.menu_text(&locale),
+  );
+
+  for (entity, toast) in &toast_q {
+    if tc - toast.time_save >= TOAST_MS {
+      commands.entity(entity).despawn();
+    }
+  }
+
+  let column = match column_q.single() {
+    Ok(column) => column,
+    Err(_) => return,
+  };
+  for achievement in std::mem::take(&mut achievements.pending) {
+    let mut fx = TextFx::new(vec![TextEffect::TYPEWRITER { ms_per_char: 30 }]);
+    fx.set(
+      tc,
+      format!(
+        "{}\n{}",
+        locale.tr("achievement unlocked"),
+        locale.tr(achievement.name())
+      ),
+    );
+    commands.entity(column).with_children(|parent| {
+      parent
+        .spawn((
+          Node {
+            padding: UiRect::all(Val::Px(8.0)),
+            ..default()
+          },
+          BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+          Toast { time_save: tc },
+        ))
+        .with_children(|parent| {
+          parent.spawn((
+            Text::default(),
+            TextFont {
+              font: asset_server.load("fonts/Monocraft.ttf"),
+              font_size: TOAST_FONT_SIZE,
+              ..default()
+            },
+            TextColor(Color::WHITE),
+            fx,
+          ));
+        });
+    });
+  }
+}
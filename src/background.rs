@@ -0,0 +1,199 @@
+use bevy::prelude::*;
+
+use crate::access::{Access, Fades, SLOW_FADE};
+use crate::camera::CameraRig;
+use crate::{Phase, WINDOW_WIDTH};
+
+// share of the way to the target covered per 60hz frame
+const FADE: f32 = 0.02;
+
+struct Layer {
+  texture: &'static str,
+  size: Vec2,
+  parallax: f32,
+  i: f32,
+  tint: Color,
+}
+
+struct Theme {
+  layers: &'static [Layer],
+  wobble: f32,
+}
+
+const THEMES: [Theme; 3] = [
+  // haskell, calm purple hills
+  Theme {
+    layers: &[
+      Layer {
+        texture: "textures/bg/hills.png",
+        size: Vec2::new(512.0, 256.0),
+        parallax: 0.2,
+        i: -130.0,
+        tint: Color::srgba(0.37, 0.31, 0.53, 1.0),
+      },
+      Layer {
+        texture: "textures/bg/lambda.png",
+        size: Vec2::new(128.0, 128.0),
+        parallax: 0.35,
+        i: 120.0,
+        tint: Color::srgba(1.0, 1.0, 1.0, 0.15),
+      },
+      Layer {
+        texture: "textures/bg/hills.png",
+        size: Vec2::new(384.0, 128.0),
+        parallax: 0.6,
+        i: -200.0,
+        tint: Color::srgba(0.29, 0.24, 0.43, 1.0),
+      },
+    ],
+    wobble: 0.0,
+  },
+  // corruption, the same hills gone green and unstable
+  Theme {
+    layers: &[
+      Layer {
+        texture: "textures/bg/hills.png",
+        size: Vec2::new(512.0, 256.0),
+        parallax: 0.2,
+        i: -130.0,
+        tint: Color::srgba(0.0, 0.3, 0.1, 1.0),
+      },
+      Layer {
+        texture: "textures/bg/lambda.png",
+        size: Vec2::new(128.0, 128.0),
+        parallax: 0.35,
+        i: 120.0,
+        tint: Color::srgba(0.0, 1.0, 0.0, 0.3),
+      },
+      Layer {
+        texture: "textures/bg/hills.png",
+        size: Vec2::new(384.0, 128.0),
+        parallax: 0.6,
+        i: -200.0,
+        tint: Color::srgba(0.0, 0.2, 0.05, 1.0),
+      },
+    ],
+    wobble: 12.0,
+  },
+  // java, enterprise office
+  Theme {
+    layers: &[
+      Layer {
+        texture: "textures/bg/office.png",
+        size: Vec2::new(512.0, 512.0),
+        parallax: 0.25,
+        i: 0.0,
+        tint: Color::srgba(0.35, 0.35, 0.4, 1.0),
+      },
+      Layer {
+        texture: "textures/bg/cubicles.png",
+        size: Vec2::new(512.0, 128.0),
+        parallax: 0.6,
+        i: -190.0,
+        tint: Color::srgba(0.55, 0.55, 0.6, 1.0),
+      },
+    ],
+    wobble: 0.0,
+  },
+];
+
+fn theme_for(phase: &Phase) -> usize {
+  match phase {
+    Phase::DEFAULT | Phase::MILESTONE => 0,
+    Phase::CORRUPT_A | Phase::CORRUPT_B => 1,
+    Phase::JAVA => 2,
+  }
+}
+
+struct LayerTiles {
+  theme: usize,
+  layer: usize,
+  entity_vec: Vec<Entity>,
+  alpha: f32,
+}
+
+#[derive(Resource, Default)]
+pub struct Background {
+  layer_vec: Vec<LayerTiles>,
+}
+
+pub fn background_setup(
+  asset_server: Res<AssetServer>,
+  mut background: ResMut<Background>,
+  mut commands: Commands,
+) {
+  let mut z = -0.09;
+
+  for (theme_n, theme) in THEMES.iter().enumerate() {
+    for (layer_n, layer) in theme.layers.iter().enumerate() {
+      // enough tiles for a view twice as wide as the virtual one, so letterbox off still covers
+      let count = (2.0 * WINDOW_WIDTH / layer.size.x).ceil() as usize + 2;
+      let entity_vec = (0..count)
+        .map(|_| {
+          commands
+            .spawn((
+              Sprite {
+                image: asset_server.load(layer.texture),
+                color: Color::NONE,
+                custom_size: Some(layer.size),
+                ..default()
+              },
+              Transform::from_xyz(0.0, layer.i, z),
+            ))
+            .id()
+        })
+        .collect();
+
+      background.layer_vec.push(LayerTiles {
+        theme: theme_n,
+        layer: layer_n,
+        entity_vec,
+        alpha: if theme_n == 0 { 1.0 } else { 0.0 },
+      });
+      z += 0.005;
+    }
+  }
+}
+
+pub fn background_update(
+  time: Res<Time>,
+  phase: Res<State<Phase>>,
+  rig: Res<CameraRig>,
+  access: Res<Access>,
+  mut background: ResMut<Background>,
+  mut tile_q: Query<(&mut Transform, &mut Sprite)>,
+) {
+  let theme_n = theme_for(phase.get());
+  let t = time.elapsed_secs();
+  // scaled by the frame time, so the crossfade takes as long at any frame rate
+  let frames = time.delta_secs() * 60.0;
+  let fade = match access.fades {
+    Fades::NORMAL => 1.0 - (1.0 - FADE).powf(frames),
+    Fades::SLOW => 1.0 - (1.0 - FADE / SLOW_FADE).powf(frames),
+    Fades::OFF => 1.0,
+  };
+
+  for tiles in &mut background.layer_vec {
+    let theme = &THEMES[tiles.theme];
+    let layer = &theme.layers[tiles.layer];
+    let target = if tiles.theme == theme_n { 1.0 } else { 0.0 };
+    tiles.alpha += (target - tiles.alpha) * fade;
+
+    let w = layer.size.x;
+    let scroll = (rig.j * layer.parallax).rem_euclid(w);
+    let left = rig.j - scroll - w * (tiles.entity_vec.len() / 2) as f32;
+
+    for (k, entity) in tiles.entity_vec.iter().enumerate() {
+      if let Ok((mut transform, mut sprite)) = tile_q.get_mut(*entity) {
+        let wobble = if access.reduced_motion {
+          0.0
+        } else {
+          theme.wobble * (t * 3.0 + k as f32 * 1.7).sin()
+        };
+        transform.translation.x = left + k as f32 * w;
+        transform.translation.y = rig.i + layer.i + wobble;
+        sprite.color = layer.tint.with_alpha(layer.tint.alpha() * tiles.alpha);
+      }
+    }
+  }
+}
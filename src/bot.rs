@@ -0,0 +1,241 @@
+use std::time::Instant;
+
+use bevy::prelude::*;
+
+use crate::headless::{headless_app, FakeClock};
+use crate::local::{Controls, Local};
+use crate::locale::Locale;
+use crate::net::Net;
+use crate::{GameEvent, Java, Phase, Player, Sim, LEVEL_WIDTH, PLAYER_HEALTH, WINDOW_HEIGHT};
+
+const ATTRACT_AFTER_MS: usize = 30000;
+const DEFLECT_RANGE_J: f32 = 40.0;
+const DODGE_RANGE_J: f32 = 70.0;
+const MAX_APPLES: usize = 16;
+
+#[derive(Resource, Default)]
+pub struct Bot {
+  pub active: bool,
+  // headless runs never hand control back
+  headless: bool,
+  // something outside, like the gym, sets the controls instead of the planner
+  external: bool,
+  idle_since: usize,
+  pub controls: Controls,
+}
+
+impl Bot {
+  pub fn headless(external: bool) -> Bot {
+    Bot {
+      active: true,
+      headless: true,
+      external,
+      ..default()
+    }
+  }
+
+  pub fn text(&self, locale: &Locale) -> String {
+    if self.active && !self.headless {
+      locale.tr("demo, press any key").to_string()
+    } else {
+      String::new()
+    }
+  }
+}
+
+// the same three inputs a player has: hop whenever possible, get under falling apples
+// while rising so they deflect, and step aside from the ones it can't reach in time
+fn plan(player: &Player, java: &Java, phase: Phase) -> Controls {
+  let mut controls = Controls::default();
+  let wall = LEVEL_WIDTH / 2.0 - player.size.x;
+
+  let threat = java
+    .obj_vec
+    .iter()
+    .filter(|obj| !obj.deflected && obj.vel_i < 0.0 && obj.i > player.i)
+    .min_by(|a, b| (a.i - player.i).total_cmp(&(b.i - player.i)));
+
+  match threat {
+    Some(obj) if phase == Phase::JAVA => {
+      let dj = obj.j - player.j;
+      let di = obj.i - player.i;
+      if player.vel_i <= 0.0 && !player.grounded && dj.abs() < DODGE_RANGE_J && di < 150.0 {
+        controls.left = dj > 0.0;
+        controls.right = dj <= 0.0;
+      } else if dj.abs() > DEFLECT_RANGE_J / 2.0 {
+        controls.left = dj < 0.0;
+        controls.right = dj > 0.0;
+      }
+      // hold the jump so it rises all the way into the apple
+      controls.jump =
+        player.grounded && !player.jump_held && dj.abs() < DEFLECT_RANGE_J && di < 250.0 ||
+          player.vel_i > 0.0 && player.jump_held;
+    }
+    _ => {
+      controls.jump = player.grounded && !player.jump_held;
+      controls.left = player.j > wall;
+      controls.right = player.j < -wall;
+    }
+  }
+  controls
+}
+
+pub fn bot_update(
+  time: Res<Time>,
+  keyboard_input: Res<ButtonInput<KeyCode>>,
+  gamepad_q: Query<&Gamepad>,
+  player: Res<Player>,
+  java: Res<Java>,
+  phase: Res<State<Phase>>,
+  mut local: ResMut<Local>,
+  net: Res<Net>,
+  mut bot: ResMut<Bot>,
+) {
+  let tc = time.elapsed().as_millis() as usize;
+
+  if !bot.headless {
+    let touched = keyboard_input.get_pressed().next().is_some() ||
+      gamepad_q
+        .iter()
+        .any(|gamepad| gamepad.get_pressed().next().is_some());
+    if touched || local.is_active() || net.is_online() {
+      bot.idle_since = tc;
+      // the human gets a fresh run rather than the demo's
+      if bot.active {
+        bot.active = false;
+        local.restart = true;
+      }
+    } else if tc - bot.idle_since >= ATTRACT_AFTER_MS {
+      bot.active = true;
+    }
+  }
+
+  if bot.active && !bot.external {
+    bot.controls = plan(&player, &java, *phase.get());
+  }
+}
+
+#[derive(Resource, Default)]
+struct Soak {
+  deaths: usize,
+  jumps: usize,
+  java_reached: bool,
+  max_apples: usize,
+}
+
+fn soak_check(
+  player: Res<Player>,
+  java: Res<Java>,
+  phase: Res<State<Phase>>,
+  transform_q: Query<&Transform>,
+  clock: Res<FakeClock>,
+  mut soak: ResMut<Soak>,
+  mut events: MessageReader<GameEvent>,
+) {
+  for event in events.read() {
+    match event {
+      GameEvent::DEATH => soak.deaths += 1,
+      GameEvent::JUMP => soak.jumps += 1,
+      _ => {}
+    }
+  }
+
+  let floor = -(WINDOW_HEIGHT / 2.0) + (WINDOW_HEIGHT * 0.1);
+  let tick = clock.tick;
+  assert!(
+    player.i.is_finite() && player.j.is_finite(),
+    "tick {}: player position is {} {}",
+    tick,
+    player.i,
+    player.j
+  );
+  assert!(
+    player.i >= floor,
+    "tick {}: player fell through the floor",
+    tick
+  );
+  assert!(
+    player.j.abs() <= LEVEL_WIDTH / 2.0,
+    "tick {}: player left the level",
+    tick
+  );
+  assert!(
+    player.health <= PLAYER_HEALTH,
+    "tick {}: health overflowed",
+    tick
+  );
+  assert!(
+    player
+      .entity
+      .is_some_and(|entity| transform_q.get(entity).is_ok()),
+    "tick {}: player entity is gone",
+    tick
+  );
+  assert!(
+    java.obj_vec.len() <= MAX_APPLES,
+    "tick {}: {} apples in flight",
+    tick,
+    java.obj_vec.len()
+  );
+  for obj in &java.obj_vec {
+    assert!(
+      obj
+        .entity
+        .is_some_and(|entity| transform_q.get(entity).is_ok()),
+      "tick {}: apple without an entity",
+      tick
+    );
+  }
+
+  soak.max_apples = soak.max_apples.max(java.obj_vec.len());
+  soak.java_reached |= *phase.get() == Phase::JAVA;
+}
+
+// the checks panic with the tick that broke
+fn soak_run(ticks: usize) -> App {
+  let mut app = headless_app(Bot::headless(false), Net::default());
+  app
+    .init_resource::<Soak>()
+    .add_systems(FixedUpdate, soak_check.after(Sim::PRESENTATION));
+  for _ in 0..ticks {
+    app.update();
+  }
+  app
+}
+
+pub fn soak(ticks: usize) {
+  let started = Instant::now();
+  let app = soak_run(ticks);
+
+  let soak = app.world().resource::<Soak>();
+  let tick = app.world().resource::<FakeClock>().tick;
+  println!(
+    "soak ok: {} ticks in {:.1}s, {} jumps, {} deaths, java reached: {}, most apples at once: {}",
+    tick,
+    started.elapsed().as_secs_f32(),
+    soak.jumps,
+    soak.deaths,
+    soak.java_reached,
+    soak.max_apples
+  );
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // the bot gets through the corruption in about 80 seconds
+  const TEST_TICKS: usize = 60 * 100;
+
+  #[test]
+  fn soak_reaches_java() {
+    let app = soak_run(TEST_TICKS);
+    let soak = app.world().resource::<Soak>();
+    assert!(soak.jumps > 0);
+    assert!(
+      soak.java_reached,
+      "java not reached in {} ticks",
+      TEST_TICKS
+    );
+  }
+}
@@ -0,0 +1,183 @@
+use bevy::camera::ScalingMode;
+use bevy::prelude::*;
+use bevy::window::{MonitorSelection, PrimaryWindow, WindowMode};
+use rand::{thread_rng, Rng};
+
+use crate::access::Access;
+use crate::local::Local;
+use crate::net::{Net, Status};
+use crate::saves;
+use crate::{
+  GameEvent, Player, Settings, LEVEL_HEIGHT, LEVEL_WIDTH, SETTINGS_PATH, WINDOW_HEIGHT,
+  WINDOW_WIDTH,
+};
+
+const BAR_SIZE: f32 = 10000.0;
+const DEAD_ZONE_J: f32 = WINDOW_WIDTH * 0.15;
+const DEAD_ZONE_I: f32 = WINDOW_HEIGHT * 0.2;
+const CAMERA_LERP: f32 = 0.1;
+const SHAKE_PX: f32 = 20.0;
+const SHAKE_DEG: f32 = 3.0;
+const SHAKE_DECAY: f32 = 0.02;
+
+#[derive(Resource, Default)]
+pub struct CameraRig {
+  pub i: f32,
+  pub j: f32,
+  trauma: f32,
+}
+
+impl CameraRig {
+  pub fn add_trauma(&mut self, trauma: f32) {
+    self.trauma = (self.trauma + trauma).min(1.0);
+  }
+}
+
+#[derive(Component)]
+pub struct MainCamera;
+
+#[derive(Component)]
+pub struct Letterbox;
+
+// the view is always WINDOW_WIDTH x WINDOW_HEIGHT units, the projection scales it to whatever the window is
+pub fn camera_setup(mut commands: Commands) {
+  commands
+    .spawn((
+      Camera2d,
+      Projection::Orthographic(OrthographicProjection {
+        scaling_mode: ScalingMode::AutoMin {
+          min_width: WINDOW_WIDTH,
+          min_height: WINDOW_HEIGHT,
+        },
+        ..OrthographicProjection::default_2d()
+      }),
+      MainCamera,
+    ))
+    .with_children(|parent| {
+      for (j, i) in [
+        (-(WINDOW_WIDTH + BAR_SIZE) / 2.0, 0.0),
+        ((WINDOW_WIDTH + BAR_SIZE) / 2.0, 0.0),
+        (0.0, -(WINDOW_HEIGHT + BAR_SIZE) / 2.0),
+        (0.0, (WINDOW_HEIGHT + BAR_SIZE) / 2.0),
+      ] {
+        parent.spawn((
+          Sprite::from_color(Color::BLACK, Vec2::new(BAR_SIZE, BAR_SIZE)),
+          Transform::from_xyz(j, i, -1.0),
+          Letterbox,
+        ));
+      }
+    });
+}
+
+pub fn camera_letterbox(
+  settings: Res<Settings>,
+  mut bar_q: Query<&mut Visibility, With<Letterbox>>,
+) {
+  if settings.is_changed() {
+    for mut visibility in &mut bar_q {
+      *visibility = if settings.letterbox {
+        Visibility::Inherited
+      } else {
+        Visibility::Hidden
+      };
+    }
+  }
+}
+
+pub fn window_controls(
+  keyboard_input: Res<ButtonInput<KeyCode>>,
+  net: Res<Net>,
+  mut window_q: Query<&mut Window, With<PrimaryWindow>>,
+  mut settings: ResMut<Settings>,
+) {
+  let before = settings.clone();
+  if keyboard_input.just_pressed(KeyCode::F11) {
+    if let Ok(mut window) = window_q.single_mut() {
+      window.mode = match window.mode {
+        WindowMode::Windowed => WindowMode::BorderlessFullscreen(MonitorSelection::Current),
+        _ => WindowMode::Windowed,
+      };
+    }
+  }
+
+  if keyboard_input.just_pressed(KeyCode::F10) {
+    settings.letterbox = !settings.letterbox;
+  }
+
+  if keyboard_input.just_pressed(KeyCode::F9) {
+    settings.post_process = !settings.post_process;
+  }
+
+  // online games keep the assists the host started with, and the letters belong to the join
+  // code while one is being typed
+  if !net.is_online() && !matches!(net.status, Status::TYPING(_)) {
+    if keyboard_input.just_pressed(KeyCode::KeyH) {
+      settings.auto_hop = !settings.auto_hop;
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyJ) {
+      settings.double_jump = !settings.double_jump;
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyK) {
+      settings.wall_jump = !settings.wall_jump;
+    }
+  }
+
+  if *settings != before {
+    saves::save(SETTINGS_PATH, &*settings);
+  }
+}
+
+fn follow(pos: f32, target: f32, dead_zone: f32, bound: f32) -> f32 {
+  let target = if target - pos > dead_zone {
+    target - dead_zone
+  } else if pos - target > dead_zone {
+    target + dead_zone
+  } else {
+    pos
+  };
+  (pos + (target - pos) * CAMERA_LERP).clamp(-bound, bound)
+}
+
+pub fn camera_follow(
+  player: Res<Player>,
+  local: Res<Local>,
+  access: Res<Access>,
+  mut rig: ResMut<CameraRig>,
+  mut events: MessageReader<GameEvent>,
+  mut camera_q: Query<&mut Transform, With<MainCamera>>,
+) {
+  for event in events.read() {
+    match event {
+      GameEvent::CORRUPT_A | GameEvent::CORRUPT_B | GameEvent::JAVA => rig.add_trauma(0.7),
+      GameEvent::DEATH | GameEvent::JAVA_DEFEATED => rig.add_trauma(1.0),
+      _ => {}
+    }
+  }
+
+  // with two players the camera follows the midpoint between them
+  let (i, j) = if local.is_active() {
+    (
+      (player.i + local.player.i) / 2.0,
+      (player.j + local.player.j) / 2.0,
+    )
+  } else {
+    (player.i, player.j)
+  };
+
+  rig.j = follow(rig.j, j, DEAD_ZONE_J, (LEVEL_WIDTH - WINDOW_WIDTH) / 2.0);
+  rig.i = follow(rig.i, i, DEAD_ZONE_I, (LEVEL_HEIGHT - WINDOW_HEIGHT) / 2.0);
+
+  let mut rng = thread_rng();
+  let shake = if access.reduced_motion {
+    0.0
+  } else {
+    rig.trauma.powi(2)
+  };
+  for mut transform in &mut camera_q {
+    transform.translation.x = rig.j + rng.gen_range(-1.0..=1.0) * shake * SHAKE_PX;
+    transform.translation.y = rig.i + rng.gen_range(-1.0..=1.0) * shake * SHAKE_PX;
+    transform.rotation =
+      Quat::from_rotation_z((rng.gen_range(-1.0..=1.0) * shake * SHAKE_DEG).to_radians());
+  }
+  rig.trauma = (rig.trauma - SHAKE_DECAY).max(0.0);
+}
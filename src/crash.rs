@@ -0,0 +1,32 @@
+// a panic on the web stops the frame loop and leaves a frozen canvas, so the page says so instead
+#[cfg(target_arch = "wasm32")]
+pub fn install() {
+  let previous = std::panic::take_hook();
+  std::panic::set_hook(Box::new(move |info| {
+    previous(info);
+    show(&info.to_string());
+  }));
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn install() {}
+
+#[cfg(target_arch = "wasm32")]
+fn show(message: &str) -> Option<()> {
+  let document = web_sys::window()?.document()?;
+  let overlay = document.create_element("pre").ok()?;
+  overlay.set_id("crash");
+  overlay
+    .set_attribute(
+      "style",
+      "position: fixed; inset: 0; margin: 0; padding: 2em; overflow: auto; white-space: pre-wrap; \
+       background: rgba(0, 0, 0, 0.85); color: #f66; font: 16px monospace; z-index: 1000;",
+    )
+    .ok()?;
+  overlay.set_text_content(Some(&format!(
+    "haskellHop crashed, reload the page to play again.\n\nIf you report it, please include this:\n\n{}",
+    message
+  )));
+  document.body()?.append_child(&overlay).ok()?;
+  Some(())
+}
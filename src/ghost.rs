@@ -0,0 +1,146 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::bot::Bot;
+use crate::local::Local;
+use crate::locale::Locale;
+use crate::net::Net;
+use crate::saves;
+use crate::{Dir, Player, CORRUPT_JUMP, PLAYER_SCALE};
+
+const BEST_PATH: &str = "saves/best.ghost.ron";
+const GHOST_ALPHA: f32 = 0.35;
+
+// i, j, facing left, jump count, one per fixed tick
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+struct Sample(f32, f32, bool, usize);
+
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq, Debug)]
+struct Run {
+  sample_vec: Vec<Sample>,
+}
+
+impl Run {
+  fn seconds(&self) -> f32 {
+    self.sample_vec.len() as f32 / 60.0
+  }
+}
+
+#[derive(Resource, Default)]
+pub struct Ghost {
+  entity: Option<Entity>,
+  // your own fastest run, kept whatever you race
+  best: Option<Run>,
+  // someone else's run from HASKELLHOP_GHOST, raced instead of your own when set
+  rival: Option<Run>,
+  recording: Run,
+  last_jump_count: usize,
+  finished: Option<f32>,
+}
+
+impl Ghost {
+  fn target(&self) -> Option<&Run> {
+    self.rival.as_ref().or(self.best.as_ref())
+  }
+
+  fn sample(&self) -> Option<Sample> {
+    let target = self.target()?;
+    let frame = self.recording.sample_vec.len().min(target.sample_vec.len());
+    target.sample_vec.get(frame.saturating_sub(1)).copied()
+  }
+
+  pub fn text(&self, locale: &Locale) -> String {
+    match (self.target(), self.finished) {
+      (Some(best), Some(seconds)) => locale.format(
+        "run {}s, ghost {}s",
+        &[
+          &format!("{:.2}", seconds),
+          &format!("{:.2}", best.seconds()),
+        ],
+      ),
+      (Some(_), None) => locale.format("ghost {}", &[&self.sample().map_or(0, |sample| sample.3)]),
+      (None, _) => String::new(),
+    }
+  }
+}
+
+pub fn ghost_setup(
+  mut commands: Commands,
+  asset_server: Res<AssetServer>,
+  mut ghost: ResMut<Ghost>,
+) {
+  ghost.best = saves::load(BEST_PATH);
+  ghost.rival = std::env::var("HASKELLHOP_GHOST")
+    .ok()
+    .and_then(|path| saves::load(&path));
+  ghost.entity = Some(
+    commands
+      .spawn((
+        Sprite {
+          image: asset_server.load("textures/haskell.png"),
+          color: Color::srgba(1.0, 1.0, 1.0, GHOST_ALPHA),
+          ..default()
+        },
+        Transform::from_scale(Vec3::new(PLAYER_SCALE, PLAYER_SCALE, 0.0)),
+        Visibility::Hidden,
+      ))
+      .id(),
+  );
+}
+
+// records solo runs from reset to CORRUPT_JUMP and replays the fastest one, or the rival's,
+// frame for frame
+pub fn ghost_update(
+  player: Res<Player>,
+  local: Res<Local>,
+  net: Res<Net>,
+  bot: Res<Bot>,
+  mut ghost: ResMut<Ghost>,
+  mut ghost_q: Query<(&mut Transform, &mut Sprite, &mut Visibility)>,
+) {
+  // demo runs are the bot's, they never make it into the best ghost
+  let solo = !local.is_active() && !net.is_online() && !bot.active;
+
+  if player.jump_count < ghost.last_jump_count {
+    ghost.recording.sample_vec.clear();
+    ghost.finished = None;
+  }
+  ghost.last_jump_count = player.jump_count;
+
+  if solo && ghost.finished.is_none() {
+    ghost.recording.sample_vec.push(Sample(
+      player.i,
+      player.j,
+      player.dir == Dir::LEFT,
+      player.jump_count,
+    ));
+
+    if player.jump_count >= CORRUPT_JUMP {
+      let seconds = ghost.recording.seconds();
+      ghost.finished = Some(seconds);
+      if ghost
+        .best
+        .as_ref()
+        .is_none_or(|best| seconds < best.seconds())
+      {
+        saves::save(BEST_PATH, &ghost.recording);
+        ghost.best = Some(ghost.recording.clone());
+      }
+    }
+  }
+
+  let sample = ghost.sample();
+  if let Some(entity) = ghost.entity {
+    if let Ok((mut transform, mut sprite, mut visibility)) = ghost_q.get_mut(entity) {
+      *visibility = if solo && sample.is_some() {
+        Visibility::Inherited
+      } else {
+        Visibility::Hidden
+      };
+      if let Some(Sample(i, j, left, _)) = sample {
+        transform.translation = Vec3::new(j, i, -0.01);
+        sprite.flip_x = left;
+      }
+    }
+  }
+}
@@ -0,0 +1,219 @@
+use std::io::{BufRead, Write};
+
+use bevy::ecs::message::{MessageCursor, Messages};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::bot::Bot;
+use crate::headless::headless_app;
+use crate::local::Controls;
+use crate::net::Net;
+use crate::{GameEvent, Java, Phase, Player};
+
+// apples past this many, nearest first, are left out of the observation
+const NEARBY_APPLES: usize = 4;
+// five minutes of ticks, an episode that long is cut off
+const MAX_STEPS: usize = 60 * 60 * 5;
+
+#[derive(Serialize, Default, Clone, PartialEq, Debug)]
+pub struct Apple {
+  // relative to the player
+  pub di: f32,
+  pub dj: f32,
+  pub vel_i: f32,
+  pub vel_j: f32,
+  pub deflected: bool,
+}
+
+#[derive(Serialize, Default, Clone, PartialEq, Debug)]
+pub struct Observation {
+  pub i: f32,
+  pub j: f32,
+  pub vel_i: f32,
+  pub vel_j: f32,
+  pub grounded: bool,
+  pub health: usize,
+  pub jump_count: usize,
+  pub phase: String,
+  // only while java is on screen
+  pub java: Option<(f32, f32)>,
+  pub java_health: usize,
+  pub apples: Vec<Apple>,
+}
+
+fn reward(event: GameEvent) -> f32 {
+  match event {
+    GameEvent::JUMP => 1.0,
+    GameEvent::DEFLECT => 5.0,
+    GameEvent::JAVA_HIT => 10.0,
+    GameEvent::JAVA_DEFEATED => 50.0,
+    GameEvent::HURT => -5.0,
+    GameEvent::DEATH => -20.0,
+    _ => 0.0,
+  }
+}
+
+// one episode of the headless simulation, a step is one 60hz tick with player one on `action`
+pub struct Env {
+  app: App,
+  reader: MessageCursor<GameEvent>,
+  steps: usize,
+}
+
+impl Env {
+  // the same seed gives the same java and power-ups for the same actions
+  pub fn new(seed: u64) -> Env {
+    let mut env = Env {
+      app: headless_app(Bot::headless(true), Net::seeded(seed)),
+      reader: default(),
+      steps: 0,
+    };
+    // the first update runs setup
+    env.app.update();
+    env.drain();
+    env
+  }
+
+  pub fn step(&mut self, action: Controls) -> (Observation, f32, bool) {
+    self.app.world_mut().resource_mut::<Bot>().controls = action;
+    self.app.update();
+    self.steps += 1;
+
+    let events = self.drain();
+    let reward = events.iter().map(|event| reward(*event)).sum();
+    let done = self.steps >= MAX_STEPS ||
+      events
+        .iter()
+        .any(|event| matches!(event, GameEvent::DEATH | GameEvent::JAVA_DEFEATED));
+    (self.observation(), reward, done)
+  }
+
+  fn drain(&mut self) -> Vec<GameEvent> {
+    let events = self.app.world().resource::<Messages<GameEvent>>();
+    self.reader.read(events).copied().collect()
+  }
+
+  fn observation(&self) -> Observation {
+    let world = self.app.world();
+    let player = world.resource::<Player>();
+    let java = world.resource::<Java>();
+    let phase = *world.resource::<State<Phase>>().get();
+
+    let mut apples = java
+      .obj_vec
+      .iter()
+      .map(|obj| Apple {
+        di: obj.i - player.i,
+        dj: obj.j - player.j,
+        vel_i: obj.vel_i,
+        vel_j: obj.vel_j,
+        deflected: obj.deflected,
+      })
+      .collect::<Vec<_>>();
+    apples.sort_by(|a, b| (a.di.hypot(a.dj)).total_cmp(&b.di.hypot(b.dj)));
+    apples.truncate(NEARBY_APPLES);
+
+    Observation {
+      i: player.i,
+      j: player.j,
+      vel_i: player.vel_i,
+      vel_j: player.vel_j,
+      grounded: player.grounded,
+      health: player.health,
+      jump_count: player.jump_count,
+      phase: phase.name().to_string(),
+      java: (phase == Phase::JAVA).then_some((java.i, java.j)),
+      java_health: java.health,
+      apples,
+    }
+  }
+}
+
+// one json object per line each way, {"reset": seed} or {"step": action bits} in,
+// {"observation": .., "reward": .., "done": ..} or {"error": ..} out
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Request {
+  Reset(u64),
+  Step(u8),
+}
+
+#[derive(Serialize)]
+struct Reply {
+  observation: Observation,
+  reward: f32,
+  done: bool,
+}
+
+#[derive(Serialize)]
+struct Failure {
+  error: String,
+}
+
+pub fn serve() -> anyhow::Result<()> {
+  let stdin = std::io::stdin();
+  let mut stdout = std::io::stdout().lock();
+  let mut env: Option<Env> = None;
+
+  for line in stdin.lock().lines() {
+    let line = line?;
+    if line.trim().is_empty() {
+      continue;
+    }
+    let reply = match serde_json::from_str::<Request>(&line) {
+      Ok(Request::Reset(seed)) => {
+        let new = Env::new(seed);
+        let observation = new.observation();
+        env = Some(new);
+        serde_json::to_string(&Reply {
+          observation,
+          reward: 0.0,
+          done: false,
+        })
+      }
+      Ok(Request::Step(bits)) => match &mut env {
+        Some(env) => {
+          let (observation, reward, done) = env.step(Controls::from_bits(bits));
+          serde_json::to_string(&Reply {
+            observation,
+            reward,
+            done,
+          })
+        }
+        None => serde_json::to_string(&Failure {
+          error: "reset before stepping".to_string(),
+        }),
+      },
+      Err(e) => serde_json::to_string(&Failure {
+        error: e.to_string(),
+      }),
+    }?;
+    writeln!(stdout, "{}", reply)?;
+    stdout.flush()?;
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn same_seed_same_episode() {
+    let mut a = Env::new(7);
+    let mut b = Env::new(7);
+    assert_eq!(a.observation(), b.observation());
+    // hop and drift right, long enough for power-ups to come and go
+    for step in 0..600 {
+      let action = Controls {
+        jump: step % 40 < 20,
+        right: step % 200 < 100,
+        ..default()
+      };
+      let (observation_a, reward_a, done_a) = a.step(action);
+      let (observation_b, reward_b, done_b) = b.step(action);
+      assert_eq!(observation_a, observation_b, "step {}", step);
+      assert_eq!((reward_a, done_a), (reward_b, done_b), "step {}", step);
+    }
+  }
+}
@@ -0,0 +1,372 @@
+use bevy::input::InputPlugin;
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+
+use crate::access::Access;
+use crate::bot::Bot;
+use crate::camera::CameraRig;
+use crate::ghost::Ghost;
+use crate::local::Local;
+use crate::locale::Locale;
+use crate::milestone::{milestone_setup, MilestoneTable, Milestones};
+use crate::net::Net;
+use crate::particle::{Burst, Particles};
+use crate::powerup::PowerUps;
+use crate::ron_asset::RonLoader;
+use crate::skin::Skins;
+use crate::tween::{TweenDone, Tweens};
+use crate::{
+  setup, simulation, GameEvent, Java, Phase, Player, Settings, Texter, BACKGROUND_COLOR,
+};
+
+#[derive(Resource, Default)]
+pub struct FakeClock {
+  pub tick: usize,
+}
+
+fn fake_clock(mut clock: ResMut<FakeClock>) {
+  clock.tick += 1;
+}
+
+// no window, renderer or audio, just the FixedUpdate sets the game runs. time advances by exactly
+// one fixed step per app.update(), so one update is one tick (the first only runs setup) and
+// player one is driven by the bot's controls
+pub fn headless_app(bot: Bot, net: Net) -> App {
+  let mut app = App::new();
+  app
+    .add_plugins((
+      MinimalPlugins,
+      AssetPlugin::default(),
+      InputPlugin,
+      StatesPlugin,
+    ))
+    .init_asset::<Image>()
+    .init_asset::<Font>()
+    .insert_resource(TimeUpdateStrategy::FixedTimesteps(1))
+    .init_resource::<Player>()
+    .init_resource::<Local>()
+    .init_resource::<Ghost>()
+    .init_resource::<Java>()
+    .init_resource::<Texter>()
+    .init_resource::<PowerUps>()
+    .init_resource::<Settings>()
+    .init_resource::<CameraRig>()
+    .init_resource::<Particles>()
+    .init_resource::<Tweens>()
+    .init_resource::<Skins>()
+    .init_resource::<Access>()
+    .init_resource::<Milestones>()
+    .init_resource::<Locale>()
+    .init_resource::<FakeClock>()
+    .insert_resource(bot)
+    .insert_resource(net)
+    .insert_resource(ClearColor(BACKGROUND_COLOR))
+    .add_message::<GameEvent>()
+    .add_message::<Burst>()
+    .add_message::<TweenDone>()
+    .init_state::<Phase>()
+    .init_asset::<MilestoneTable>()
+    .register_asset_loader(RonLoader::<MilestoneTable>::new("milestones.ron"))
+    // milestones hold the score label, which holds off the corruption, so they run here too
+    .add_systems(Startup, (setup, milestone_setup))
+    .add_systems(First, fake_clock);
+  simulation(&mut app);
+  app.finish();
+  app.cleanup();
+  app
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::mpsc::{Receiver, Sender};
+
+  use super::*;
+  use crate::local::Controls;
+  use crate::net::net_lobby;
+  use crate::{Obj, CORRUPT_B_FADE_MS, CORRUPT_JUMP, JAVA_HEALTH, PLAYER_HEALTH};
+
+  // player one stands still, the tests set up whatever they need by hand
+  fn app() -> App {
+    let mut app = headless_app(Bot::headless(true), Net::default());
+    app.update();
+    app
+  }
+
+  fn phase(app: &App) -> Phase {
+    *app.world().resource::<State<Phase>>().get()
+  }
+
+  fn tick_until(app: &mut App, want: Phase, max: usize) -> usize {
+    for tick in 1..=max {
+      app.update();
+      if phase(app) == want {
+        return tick;
+      }
+    }
+    panic!("no {:?} within {} ticks", want, max);
+  }
+
+  fn corrupt(app: &mut App) {
+    app.world_mut().resource_mut::<Player>().jump_count = CORRUPT_JUMP;
+    tick_until(app, Phase::CORRUPT_A, 120);
+  }
+
+  fn reach_java(app: &mut App) {
+    corrupt(app);
+    tick_until(app, Phase::JAVA, 60 * 30);
+    // java is spawned on the way in
+    app.update();
+  }
+
+  fn throw(app: &mut App, i: f32, j: f32, deflected: bool) {
+    let entity = app.world_mut().spawn(Transform::from_xyz(j, i, 0.0)).id();
+    app.world_mut().resource_mut::<Java>().obj_vec.push(Obj {
+      entity: Some(entity),
+      i,
+      j,
+      vel_i: -1.0,
+      scale: 1.0,
+      size: Vec2::splat(20.0),
+      deflected,
+      ..default()
+    });
+  }
+
+  fn press(app: &mut App, jump: bool) {
+    app.world_mut().resource_mut::<Bot>().controls = Controls { jump, ..default() };
+    app.update();
+  }
+
+  fn player(app: &App) -> &Player {
+    app.world().resource::<Player>()
+  }
+
+  fn standing() -> App {
+    let mut app = app();
+    for _ in 0..30 {
+      press(&mut app, false);
+    }
+    assert!(player(&app).grounded);
+    app
+  }
+
+  #[test]
+  fn coyote_time_allows_a_late_jump() {
+    for (wait, jumps) in [(0, 1), (10, 0)] {
+      let mut app = standing();
+      // knocked up off the floor rather than jumping
+      let mut lifted = app.world_mut().resource_mut::<Player>();
+      lifted.i += 100.0;
+      lifted.grounded = false;
+      for _ in 0..wait {
+        press(&mut app, false);
+      }
+      press(&mut app, true);
+      assert_eq!(
+        player(&app).jump_count,
+        jumps,
+        "pressed after {} ticks",
+        wait
+      );
+    }
+  }
+
+  #[test]
+  fn jump_buffer_catches_an_early_press() {
+    // the buffer is about seven ticks long
+    for (early, jumps) in [(3, 2), (20, 1)] {
+      let mut app = standing();
+      let floor = player(&app).i;
+      press(&mut app, true);
+      assert_eq!(player(&app).jump_count, 1);
+      while player(&app).vel_i > 0.0 {
+        press(&mut app, true);
+      }
+      // falling, wait until it's about `early` ticks off the floor
+      while player(&app).i - floor > -player(&app).vel_i * early as f32 {
+        press(&mut app, false);
+      }
+      press(&mut app, true);
+      for _ in 0..early + 5 {
+        press(&mut app, false);
+      }
+      assert_eq!(
+        player(&app).jump_count,
+        jumps,
+        "pressed {} ticks early",
+        early
+      );
+    }
+  }
+
+  #[test]
+  fn no_jump_on_the_first_online_frame() {
+    let mut client = Client::new();
+    client.inbox.send("START 0 coop 7 0".to_string()).unwrap();
+    client.tick(Controls::default());
+    client.tick(Controls::default());
+    // the run reset on that frame zeroes the jump counts, so look at where they're heading
+    let world = client.app.world();
+    assert!(world.resource::<Player>().vel_i <= 0.0);
+    assert!(world.resource::<Local>().player.vel_i <= 0.0);
+  }
+
+  #[test]
+  fn corrupts_at_the_jump_threshold() {
+    let mut app = app();
+    app.world_mut().resource_mut::<Player>().jump_count = CORRUPT_JUMP - 1;
+    for _ in 0..120 {
+      app.update();
+      assert_ne!(phase(&app), Phase::CORRUPT_A);
+    }
+    corrupt(&mut app);
+  }
+
+  #[test]
+  fn java_follows_the_corrupt_b_fade() {
+    let mut app = app();
+    corrupt(&mut app);
+    tick_until(&mut app, Phase::CORRUPT_B, 60 * 10);
+    let ticks = tick_until(&mut app, Phase::JAVA, 60 * 10);
+    assert!(ticks * 1000 >= CORRUPT_B_FADE_MS * 60);
+  }
+
+  #[test]
+  fn deflected_apples_hurt_java() {
+    let mut app = app();
+    reach_java(&mut app);
+    let (i, j, health) = {
+      let java = app.world().resource::<Java>();
+      (java.i, java.j, java.health)
+    };
+    assert_eq!(health, JAVA_HEALTH);
+    throw(&mut app, i, j, true);
+    app.update();
+    assert_eq!(app.world().resource::<Java>().health, health - 1);
+  }
+
+  #[test]
+  fn falling_apples_hurt_the_player() {
+    let mut app = app();
+    reach_java(&mut app);
+    let (i, j) = {
+      let mut player = app.world_mut().resource_mut::<Player>();
+      player.shield = false;
+      player.hurt_until = 0;
+      (player.i, player.j)
+    };
+    assert_eq!(app.world().resource::<Player>().health, PLAYER_HEALTH);
+    throw(&mut app, i, j, false);
+    app.update();
+    assert_eq!(app.world().resource::<Player>().health, PLAYER_HEALTH - 1);
+  }
+
+  // one client of an online game, with the test standing in for the relay
+  struct Client {
+    app: App,
+    inbox: Sender<String>,
+    outbox: Receiver<String>,
+  }
+
+  impl Client {
+    fn new() -> Client {
+      let (net, inbox, outbox) = Net::loopback();
+      let mut app = headless_app(Bot::headless(true), net);
+      app.add_systems(Update, net_lobby);
+      app.update();
+      Client { app, inbox, outbox }
+    }
+
+    fn tick(&mut self, controls: Controls) {
+      self.app.world_mut().resource_mut::<Bot>().controls = controls;
+      self.app.update();
+    }
+
+    // what both machines simulate, the local player is whichever slot this client has
+    fn state(&self, slot: usize) -> String {
+      let world = self.app.world();
+      let local = world.resource::<Local>();
+      let mut players = [world.resource::<Player>(), &local.player];
+      if slot == 1 {
+        players.reverse();
+      }
+      let java = world.resource::<Java>();
+      let texter = world.resource::<Texter>();
+      format!(
+        "{:?} {} {:?} {:?} {} {} {} {}",
+        phase(&self.app),
+        texter.angle,
+        players.map(|p| (p.i, p.j, p.vel_i, p.vel_j, p.jump_count, p.health)),
+        java
+          .obj_vec
+          .iter()
+          .map(|obj| (obj.i, obj.j))
+          .collect::<Vec<_>>(),
+        java.j,
+        java.health,
+        java.time_save,
+        texter.time_save,
+      )
+    }
+  }
+
+  #[test]
+  fn lockstep_clients_agree() {
+    let mut clients = [Client::new(), Client::new()];
+    // the second machine has been playing offline, nothing of that may carry into the game
+    clients[1]
+      .app
+      .world_mut()
+      .resource_mut::<Player>()
+      .jump_count = CORRUPT_JUMP - 10;
+    for tick in 0..200 {
+      clients[1].tick(Controls {
+        jump: tick % 30 < 15,
+        ..default()
+      });
+    }
+    for (slot, client) in clients.iter().enumerate() {
+      client
+        .inbox
+        .send(format!("START {} coop 7 4", slot))
+        .unwrap();
+    }
+    for client in &mut clients {
+      client.tick(Controls::default());
+    }
+
+    let mut corrupted = false;
+    for tick in 0..60 * 40 {
+      let controls = [
+        Controls {
+          jump: tick % 40 < 20,
+          right: tick % 300 < 100,
+          left: tick % 300 >= 200,
+        },
+        Controls {
+          jump: tick % 46 < 23,
+          left: tick % 240 < 80,
+          right: tick % 240 >= 160,
+        },
+      ];
+      for (slot, client) in clients.iter_mut().enumerate() {
+        client.tick(controls[slot]);
+      }
+      // the relay's part, stamping each input with the slot it came from
+      for slot in 0..2 {
+        let lines = clients[slot].outbox.try_iter().collect::<Vec<_>>();
+        for line in lines {
+          let rest = line.strip_prefix("INPUT ").unwrap();
+          clients[1 - slot]
+            .inbox
+            .send(format!("INPUT {} {}", slot, rest))
+            .unwrap();
+        }
+      }
+      assert_eq!(clients[0].state(0), clients[1].state(1), "tick {}", tick);
+      corrupted |= phase(&clients[0].app) == Phase::CORRUPT_B;
+    }
+    assert!(corrupted, "the test never got as far as the corruption");
+  }
+}
@@ -0,0 +1,195 @@
+use bevy::color::palettes::css;
+use bevy::prelude::*;
+
+use crate::bot::Bot;
+use crate::ghost::Ghost;
+use crate::local::Local;
+use crate::locale::Locale;
+use crate::net::Net;
+use crate::powerup::PowerUps;
+use crate::speedrun::{Speedrun, SplitTable};
+use crate::text_fx::{TextEffect, TextFx};
+use crate::{Java, Phase, Player, Texter, JAVA_HEALTH};
+
+const HUD_FONT_SIZE: f32 = 20.0;
+const BOSS_BAR_WIDTH: f32 = 200.0;
+
+#[derive(Component)]
+pub enum HudText {
+  SCORE,
+  BEST,
+  PHASE,
+  MESSAGE,
+  NET,
+  GHOST,
+  DEMO,
+  LIVES,
+  SCORE_2,
+  LIVES_2,
+  TIMERS,
+  SPLITS,
+  BOSS,
+}
+
+#[derive(Component)]
+pub struct BossHud;
+
+#[derive(Component)]
+pub struct BossBar;
+
+// each column lists its lines top to bottom along the top of the screen
+pub fn hud_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+  let text_font = TextFont {
+    font: asset_server.load("fonts/Monocraft.ttf"),
+    font_size: HUD_FONT_SIZE,
+    ..default()
+  };
+  let text = |colour: Color| (Text::default(), text_font.clone(), TextColor(colour));
+
+  let column = |align_items| Node {
+    flex_direction: FlexDirection::Column,
+    align_items,
+    ..default()
+  };
+
+  commands
+    .spawn(Node {
+      width: Val::Percent(100.0),
+      height: Val::Percent(100.0),
+      justify_content: JustifyContent::SpaceBetween,
+      align_items: AlignItems::FlexStart,
+      padding: UiRect::all(Val::Px(10.0)),
+      ..default()
+    })
+    .with_children(|parent| {
+      parent
+        .spawn(column(AlignItems::FlexStart))
+        .with_children(|parent| {
+          for hud_text in [HudText::SCORE, HudText::BEST, HudText::PHASE] {
+            parent.spawn((text(Color::WHITE), hud_text));
+          }
+          parent.spawn((
+            text(Color::WHITE),
+            HudText::MESSAGE,
+            TextFx::new(vec![TextEffect::TYPEWRITER { ms_per_char: 30 }]),
+          ));
+          parent.spawn((text(Color::WHITE), HudText::NET));
+          parent.spawn((text(Color::srgba(1.0, 1.0, 1.0, 0.6)), HudText::GHOST));
+          parent.spawn((text(Color::WHITE), HudText::DEMO));
+        });
+
+      parent
+        .spawn((column(AlignItems::Center), BossHud))
+        .with_children(|parent| {
+          parent.spawn((text(Color::WHITE), HudText::BOSS, BossHud));
+          parent
+            .spawn((
+              Node {
+                width: Val::Px(BOSS_BAR_WIDTH),
+                height: Val::Px(HUD_FONT_SIZE / 2.0),
+                ..default()
+              },
+              BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
+              BossHud,
+            ))
+            .with_children(|parent| {
+              parent.spawn((
+                Node {
+                  width: Val::Percent(100.0),
+                  height: Val::Percent(100.0),
+                  ..default()
+                },
+                BackgroundColor(css::ORANGE_RED.into()),
+                BossHud,
+                BossBar,
+              ));
+            });
+        });
+
+      parent
+        .spawn(column(AlignItems::FlexEnd))
+        .with_children(|parent| {
+          parent.spawn((text(css::RED.into()), HudText::LIVES));
+          parent.spawn((text(Color::WHITE), HudText::SCORE_2));
+          parent.spawn((text(css::ORANGE.into()), HudText::LIVES_2));
+          parent.spawn((text(Color::WHITE), HudText::TIMERS));
+          parent.spawn((text(Color::WHITE), HudText::SPLITS));
+        });
+    });
+}
+
+pub fn hud_update(
+  time: Res<Time>,
+  player: Res<Player>,
+  local: Res<Local>,
+  net: Res<Net>,
+  ghost: Res<Ghost>,
+  bot: Res<Bot>,
+  java: Res<Java>,
+  texter: Res<Texter>,
+  phase: Res<State<Phase>>,
+  powerups: Res<PowerUps>,
+  speedrun: Res<Speedrun>,
+  split_tables: Res<Assets<SplitTable>>,
+  locale: Res<Locale>,
+  mut text_q: Query<(&mut Text, &HudText, Option<&mut TextFx>)>,
+  mut boss_q: Query<&mut Visibility, With<BossHud>>,
+  mut bar_q: Query<&mut Node, With<BossBar>>,
+) {
+  let tc = time.elapsed().as_millis() as usize;
+  // the simulation's own timers are on its clock, tc only paces the text effects
+  let now = net.clock(tc);
+
+  for (mut text, hud_text, fx) in &mut text_q {
+    let value = match hud_text {
+      HudText::SCORE if local.is_active() => locale.format("p1 score {}", &[&player.jump_count]),
+      HudText::SCORE => locale.format("score {}", &[&player.jump_count]),
+      HudText::BEST => locale.format("best {}", &[&player.best_jump_count]),
+      HudText::PHASE => locale.format(
+        "phase {} ({})",
+        &[
+          &locale.tr(phase.get().name()),
+          &locale.tr(local.mode.name()),
+        ],
+      ),
+      HudText::MESSAGE => match (local.winner_message(now, &locale), &texter.milestone) {
+        (Some(message), _) => message,
+        (None, Some(milestone))
+          if *phase.get() != Phase::MILESTONE &&
+            now.saturating_sub(texter.milestone_save) < milestone.duration_ms =>
+        {
+          milestone.message.clone()
+        }
+        _ => String::new(),
+      },
+      HudText::NET => net.status_text(&locale),
+      HudText::GHOST => ghost.text(&locale),
+      HudText::DEMO => bot.text(&locale),
+      HudText::LIVES => "<3 ".repeat(player.health),
+      HudText::SCORE_2 if local.is_active() => {
+        locale.format("p2 score {}", &[&local.player.jump_count])
+      }
+      HudText::LIVES_2 if local.is_active() => "<3 ".repeat(local.player.health),
+      HudText::SCORE_2 | HudText::LIVES_2 => String::new(),
+      HudText::TIMERS => powerups.timers(now, &locale),
+      HudText::SPLITS => speedrun.text(&split_tables, &locale),
+      HudText::BOSS => locale.tr("java").to_string(),
+    };
+    match fx {
+      Some(mut fx) => fx.set(tc, value),
+      None => text.0 = value,
+    }
+  }
+
+  for mut visibility in &mut boss_q {
+    *visibility = if *phase.get() == Phase::JAVA {
+      Visibility::Inherited
+    } else {
+      Visibility::Hidden
+    };
+  }
+
+  for mut node in &mut bar_q {
+    node.width = Val::Percent(100.0 * java.health as f32 / JAVA_HEALTH as f32);
+  }
+}
@@ -0,0 +1,227 @@
+use bevy::math::bounding::{Aabb2d, IntersectsVolume};
+use bevy::prelude::*;
+
+use crate::locale::Locale;
+use crate::net::Net;
+use crate::{despawn, Player, PLAYER_HEALTH, PLAYER_SCALE, WINDOW_HEIGHT, WINDOW_WIDTH};
+
+pub const VERSUS_JUMPS: usize = 100;
+const STOMP_BOUNCE_VEL: f32 = 8.0;
+const STOMP_KNOCK_VEL: f32 = 12.0;
+const WINNER_MS: usize = 3000;
+const STICK_DEAD_ZONE: f32 = 0.3;
+
+// left, right, jump, jump
+const KEYS: [[KeyCode; 4]; 2] = [
+  [KeyCode::KeyA, KeyCode::KeyD, KeyCode::KeyW, KeyCode::Space],
+  [
+    KeyCode::ArrowLeft,
+    KeyCode::ArrowRight,
+    KeyCode::ArrowUp,
+    KeyCode::Enter,
+  ],
+];
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum Mode {
+  #[default]
+  SOLO,
+  COOP,
+  VERSUS,
+}
+
+impl Mode {
+  pub fn name(self) -> &'static str {
+    match self {
+      Mode::SOLO => "solo",
+      Mode::COOP => "co-op",
+      Mode::VERSUS => "versus",
+    }
+  }
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct Controls {
+  pub left: bool,
+  pub right: bool,
+  pub jump: bool,
+}
+
+impl Controls {
+  pub fn bits(self) -> u8 {
+    self.left as u8 | (self.right as u8) << 1 | (self.jump as u8) << 2
+  }
+
+  pub fn from_bits(bits: u8) -> Controls {
+    Controls {
+      left: bits & 1 != 0,
+      right: bits & 2 != 0,
+      jump: bits & 4 != 0,
+    }
+  }
+}
+
+// player one is the `Player` resource, this holds the optional second player and the mode
+#[derive(Resource, Default)]
+pub struct Local {
+  pub mode: Mode,
+  pub player: Player,
+  pub restart: bool,
+  pub winner: Option<(usize, usize)>,
+}
+
+impl Local {
+  pub fn is_active(&self) -> bool {
+    self.mode != Mode::SOLO
+  }
+
+  // the jump count that drives phases and milestones
+  pub fn team_jumps(&self, player: &Player) -> usize {
+    match self.mode {
+      Mode::SOLO => player.jump_count,
+      Mode::COOP => player.jump_count + self.player.jump_count,
+      Mode::VERSUS => player.jump_count.max(self.player.jump_count),
+    }
+  }
+
+  pub fn score(&self, player: &Player) -> String {
+    match self.mode {
+      Mode::VERSUS => format!("{} - {}", player.jump_count, self.player.jump_count),
+      _ => self.team_jumps(player).to_string(),
+    }
+  }
+
+  pub fn winner_message(&self, tc: usize, locale: &Locale) -> Option<String> {
+    match self.winner {
+      Some((n, time_save)) if tc.saturating_sub(time_save) < WINNER_MS => {
+        Some(locale.format("p{} wins", &[&n]))
+      }
+      _ => None,
+    }
+  }
+}
+
+pub fn controls(
+  n: usize,
+  keyboard_input: &ButtonInput<KeyCode>,
+  gamepad_q: &Query<&Gamepad>,
+) -> Controls {
+  let [left, right, jump_a, jump_b] = KEYS[n];
+  let mut controls = Controls {
+    left: keyboard_input.pressed(left),
+    right: keyboard_input.pressed(right),
+    jump: keyboard_input.pressed(jump_a) || keyboard_input.pressed(jump_b),
+  };
+
+  // the nth connected pad belongs to the nth player
+  if let Some(gamepad) = gamepad_q.iter().nth(n) {
+    let stick = gamepad.get(GamepadAxis::LeftStickX).unwrap_or(0.0);
+    controls.left |= stick < -STICK_DEAD_ZONE || gamepad.pressed(GamepadButton::DPadLeft);
+    controls.right |= stick > STICK_DEAD_ZONE || gamepad.pressed(GamepadButton::DPadRight);
+    controls.jump |= gamepad.pressed(GamepadButton::South);
+  }
+  controls
+}
+
+// a player standing on the floor at the left or right spawn
+pub fn fresh_player(right: bool) -> Player {
+  let j = (WINDOW_WIDTH / 2.0) - (WINDOW_HEIGHT * 0.1);
+  Player {
+    i: -(WINDOW_HEIGHT / 2.0) + (WINDOW_HEIGHT * 0.1),
+    j: if right { j } else { -j },
+    scale: PLAYER_SCALE,
+    size: Vec2::new(100.0, 100.0),
+    deflect_scale: 1.0,
+    health: PLAYER_HEALTH,
+    ..default()
+  }
+}
+
+// switches mode and restarts the run, spawning or removing the second player
+pub fn set_mode(
+  local: &mut Local,
+  mode: Mode,
+  commands: &mut Commands,
+  asset_server: &AssetServer,
+) {
+  local.mode = mode;
+  local.restart = true;
+
+  despawn(commands, local.player.entity.take());
+  if !local.is_active() {
+    return;
+  }
+
+  // the second player is an ocaml-orange haskell starting from the other side
+  local.player = fresh_player(true);
+  local.player.entity = Some(
+    commands
+      .spawn((
+        Sprite::from_image(asset_server.load("textures/haskell.png")),
+        Transform {
+          scale: Vec3::new(local.player.scale, local.player.scale, 0.0),
+          translation: Vec3::new(local.player.j, local.player.i, 0.0),
+          ..default()
+        },
+      ))
+      .id(),
+  );
+}
+
+pub fn local_mode(
+  keyboard_input: Res<ButtonInput<KeyCode>>,
+  asset_server: Res<AssetServer>,
+  net: Res<Net>,
+  mut local: ResMut<Local>,
+  mut commands: Commands,
+) {
+  // online sessions pick their mode in the lobby
+  if !keyboard_input.just_pressed(KeyCode::F1) || net.is_online() {
+    return;
+  }
+
+  let mode = match local.mode {
+    Mode::SOLO => Mode::COOP,
+    Mode::COOP => Mode::VERSUS,
+    Mode::VERSUS => Mode::SOLO,
+  };
+  set_mode(&mut local, mode, &mut commands, &asset_server);
+}
+
+// versus only, landing on the other player bounces you and throws them sideways
+pub fn local_versus(mut player: ResMut<Player>, mut local: ResMut<Local>) {
+  if local.mode != Mode::VERSUS {
+    return;
+  }
+
+  let (a, b) = (&mut *player, &mut local.player);
+  let a_box = Aabb2d::new(Vec2::new(a.j, a.i), a.size / 2.0);
+  let b_box = Aabb2d::new(Vec2::new(b.j, b.i), b.size / 2.0);
+  if !a_box.intersects(&b_box) {
+    return;
+  }
+
+  let (top, bottom) = if a.i > b.i { (a, b) } else { (b, a) };
+  if top.vel_i < 0.0 {
+    top.vel_i = STOMP_BOUNCE_VEL;
+    bottom.knock_vel_j = (bottom.j - top.j).signum() * STOMP_KNOCK_VEL;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn controls_round_trip_through_bits() {
+    for bits in 0..8 {
+      assert_eq!(Controls::from_bits(bits).bits(), bits);
+    }
+    let jump_left = Controls {
+      left: true,
+      jump: true,
+      ..default()
+    };
+    assert_eq!(jump_left.bits(), 5);
+  }
+}
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use serde::{Deserialize, Serialize};
+
+use crate::saves;
+
+const RECORD_PATH: &str = "saves/locale.ron";
+// assets/locale/<code>.locale.ron, F8 cycles through them in this order
+const LANGUAGES: [&str; 2] = ["en", "de"];
+
+// the english text is the key, so a string missing from a table shows up in english
+#[derive(Asset, TypePath, Deserialize)]
+pub struct StringTable {
+  // for scripts Monocraft doesn't cover. text stays in Monocraft and the glyph shaper takes
+  // whatever Monocraft is missing, glyph by glyph, from any of these
+  #[serde(default)]
+  pub fonts: Vec<String>,
+  #[serde(default)]
+  pub strings: HashMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Record {
+  language: String,
+}
+
+#[derive(Resource, Default)]
+pub struct Locale {
+  handles: Vec<Handle<StringTable>>,
+  current: usize,
+  // a copy of the current table, so lookups don't need the asset storage
+  strings: HashMap<String, String>,
+  fallbacks: Vec<Handle<Font>>,
+}
+
+// a blank character in a fallback font. bevy only hands a font to the glyph shaper once some
+// text is drawn with it, until then nothing else could fall back to it
+#[derive(Component)]
+pub struct FallbackFont;
+
+impl Locale {
+  pub fn tr<'a>(&'a self, key: &'a str) -> &'a str {
+    self.strings.get(key).map_or(key, |value| value.as_str())
+  }
+
+  // fills each {} in the translated text with the next argument
+  pub fn format(&self, key: &str, args: &[&dyn Display]) -> String {
+    let mut parts = self.tr(key).split("{}");
+    let mut text = parts.next().unwrap_or_default().to_string();
+    for (k, part) in parts.enumerate() {
+      if let Some(arg) = args.get(k) {
+        text += &arg.to_string();
+      }
+      text += part;
+    }
+    text
+  }
+}
+
+pub fn locale_setup(mut locale: ResMut<Locale>, asset_server: Res<AssetServer>) {
+  locale.handles = LANGUAGES
+    .iter()
+    .map(|code| asset_server.load(format!("locale/{}.locale.ron", code)))
+    .collect();
+  let record = saves::load::<Record>(RECORD_PATH).unwrap_or_default();
+  locale.current = LANGUAGES
+    .iter()
+    .position(|code| *code == record.language)
+    .unwrap_or(0);
+}
+
+pub fn locale_update(
+  keyboard_input: Res<ButtonInput<KeyCode>>,
+  tables: Res<Assets<StringTable>>,
+  asset_server: Res<AssetServer>,
+  mut locale: ResMut<Locale>,
+  mut window_q: Query<&mut Window, With<PrimaryWindow>>,
+  mut commands: Commands,
+) {
+  if keyboard_input.just_pressed(KeyCode::F8) {
+    locale.current = (locale.current + 1) % LANGUAGES.len();
+    saves::save(
+      RECORD_PATH,
+      &Record {
+        language: LANGUAGES[locale.current].to_string(),
+      },
+    );
+  }
+
+  let table = match locale
+    .handles
+    .get(locale.current)
+    .and_then(|handle| tables.get(handle))
+  {
+    Some(table) => table,
+    None => return,
+  };
+  if locale.strings != table.strings {
+    locale.strings = table.strings.clone();
+  }
+
+  // a font that never loads leaves its glyphs missing, everything else still draws
+  for path in &table.fonts {
+    let font = asset_server.load::<Font>(path.clone());
+    if locale.fallbacks.contains(&font) {
+      continue;
+    }
+    locale.fallbacks.push(font.clone());
+    commands.spawn((
+      Text2d::new(" "),
+      TextFont {
+        font,
+        font_size: 1.0,
+        ..default()
+      },
+      TextColor(Color::NONE),
+      FallbackFont,
+    ));
+  }
+
+  if let Ok(mut window) = window_q.single_mut() {
+    let title = locale.tr("haskellHop");
+    if window.title != title {
+      window.title = title.to_string();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn format_fills_placeholders_in_order() {
+    let locale = Locale {
+      strings: HashMap::from([("{} of {}".to_string(), "{} von {}".to_string())]),
+      ..default()
+    };
+    assert_eq!(locale.format("{} of {}", &[&1, &"zwei"]), "1 von zwei");
+    // missing strings stay in english
+    assert_eq!(locale.format("score {}", &[&42]), "score 42");
+    // spare placeholders stay empty and spare arguments are dropped
+    assert_eq!(locale.format("{} of {}", &[&1]), "1 von ");
+    assert_eq!(locale.format("p{}", &[&1, &2]), "p1");
+  }
+}
@@ -1,53 +1,195 @@
+#![allow(
+  non_camel_case_types,
+  clippy::upper_case_acronyms,
+  clippy::too_many_arguments
+)]
+
+use bevy::color::palettes::css;
+use bevy::ecs::component::Mutable;
+use bevy::math::bounding::{Aabb2d, IntersectsVolume};
 use bevy::prelude::*;
-use bevy::sprite::collide_aabb::collide;
-use itertools::Itertools;
-use rand::{prelude::SliceRandom, thread_rng, Rng};
-// use bevy::prelude::stage::*;
-use bevy::time::{FixedTimestep, FixedTimesteps};
+use bevy::render::{ExtractSchedule, RenderApp};
+use bevy::sprite_render::Material2dPlugin;
 use bevy::window::PresentMode;
+use rand::{prelude::SliceRandom, thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+
+mod access;
+mod achievement;
+mod background;
+mod bot;
+mod camera;
+mod crash;
+mod ghost;
+mod gym;
+mod headless;
+mod hud;
+mod local;
+mod locale;
+mod menu;
+mod milestone;
+mod net;
+mod particle;
+mod postfx;
+mod powerup;
+mod ron_asset;
+mod saves;
+mod skin;
+mod speedrun;
+mod text_fx;
+mod tween;
 
-const BACKGROUND_COLOR: Color = Color::rgb(0.7, 0.3, 0.3);
+use access::{access_setup, access_update, Access};
+use achievement::{achievement_setup, achievement_ui, achievement_update, Achievements};
+use background::{background_setup, background_update, Background};
+use bot::{bot_update, Bot};
+use camera::{camera_follow, camera_letterbox, camera_setup, window_controls, CameraRig};
+use ghost::{ghost_setup, ghost_update, Ghost};
+use hud::{hud_setup, hud_update};
+use local::{local_mode, local_versus, Controls, Local, Mode, VERSUS_JUMPS};
+use locale::{locale_setup, locale_update, Locale, StringTable};
+use milestone::{
+  milestone_setup, milestone_update, Animation, Milestone, MilestoneTable, Milestones,
+};
+use net::{net_lobby, Clock, Net};
+use particle::{particle_emit, particle_update, Burst, BurstKind, Particles};
+use postfx::{
+  postfx_resize, postfx_setup, postfx_toggle, postfx_update, postfx_watch, GlitchMaterial, PostFx,
+};
+use powerup::{powerup_update, PowerUps};
+use ron_asset::RonLoader;
+use skin::{skin_setup, skin_update, SkinTable, Skins};
+use speedrun::{speedrun_setup, speedrun_update, Speedrun, SplitTable};
+use text_fx::{text_fx_update, TextEffect, TextFx};
+use tween::{tween_update, Ease, Tween, TweenDone, TweenTag, TweenTarget, Tweens};
+
+const BACKGROUND_COLOR: Color = Color::srgb(0.7, 0.3, 0.3);
 const PLAYER_SCALE: f32 = 0.15;
 const WINDOW_HEIGHT: f32 = 500.0;
 const WINDOW_WIDTH: f32 = 1000.0;
+const LEVEL_HEIGHT: f32 = WINDOW_HEIGHT;
+const LEVEL_WIDTH: f32 = WINDOW_WIDTH * 3.0;
+const LEVEL_MARKER_GAP: f32 = 250.0;
 const GRAVITY: f32 = 9.81;
 const FRICTION: f32 = 0.7;
 const CORRUPT_JUMP: usize = 80;
+const JUMP_VEL: f32 = 10.0;
+const JUMP_RELEASE_VEL: f32 = 4.0;
+const JUMP_BUFFER_MS: usize = 120;
+const COYOTE_MS: usize = 100;
+const WALL_JUMP_VEL: f32 = 8.0;
+const PLAYER_HEALTH: usize = 3;
+const JAVA_HEALTH: usize = 10;
+const CORRUPT_A_FADE_MS: usize = 2000;
+const CORRUPT_B_FADE_MS: usize = 5500;
+const JAVA_FADE_IN_MS: usize = 16000;
+// done well before the score fade hands over to java_move, which places java itself
+const JAVA_DROP_MS: usize = 3000;
+const INVINCIBLE_MS: usize = 1500;
+const BLINK_MS: usize = 100;
+const KNOCKBACK_VEL: f32 = 8.0;
+const GLYPH_MS: usize = 500;
+const SOAK_TICKS: usize = 20000;
+const SETTINGS_PATH: &str = "saves/settings.ron";
 
-#[derive(PartialEq, Debug)]
-enum State {
-  NICE,
+// read through State<Phase> and changed through NextState<Phase>, see apply_phase
+#[derive(States, Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+enum Phase {
+  MILESTONE,
+  #[default]
   DEFAULT,
   CORRUPT_B,
   CORRUPT_A,
   JAVA,
 }
 
-impl Default for State {
-  fn default() -> State {
-    State::DEFAULT
+impl Phase {
+  fn name(&self) -> &'static str {
+    match self {
+      Phase::MILESTONE => "haskell",
+      Phase::DEFAULT => "haskell",
+      Phase::CORRUPT_A => "corrupt",
+      Phase::CORRUPT_B => "c0rrupt",
+      Phase::JAVA => "java",
+    }
   }
 }
 
-#[derive(PartialEq, Eq)]
+#[derive(PartialEq, Eq, Default)]
 enum Dir {
   LEFT,
+  #[default]
   RIGHT,
 }
 
-impl Default for Dir {
-  fn default() -> Dir {
-    Dir::RIGHT
+#[derive(Resource, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct Settings {
+  auto_hop: bool,
+  double_jump: bool,
+  wall_jump: bool,
+  letterbox: bool,
+  post_process: bool,
+}
+
+impl Default for Settings {
+  fn default() -> Settings {
+    Settings {
+      auto_hop: false,
+      double_jump: false,
+      wall_jump: true,
+      letterbox: true,
+      // webgl2 can't be trusted with the render-to-texture pass, it stays opt-in there
+      post_process: !cfg!(target_arch = "wasm32"),
+    }
+  }
+}
+
+impl Settings {
+  // the movement assists as bits, online games all play with the host's
+  fn assists(&self) -> u8 {
+    self.auto_hop as u8 | (self.double_jump as u8) << 1 | (self.wall_jump as u8) << 2
+  }
+
+  fn with_assists(&self, bits: u8) -> Settings {
+    Settings {
+      auto_hop: bits & 1 != 0,
+      double_jump: bits & 2 != 0,
+      wall_jump: bits & 4 != 0,
+      ..self.clone()
+    }
   }
 }
 
-#[derive(Default, PartialEq)]
+#[derive(Message, Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+enum GameEvent {
+  JUMP,
+  LAND,
+  HURT,
+  DEFLECT,
+  JAVA_HIT,
+  JAVA_DEFEATED,
+  DEATH,
+  POWERUP,
+  CORRUPT_A,
+  CORRUPT_B,
+  JAVA,
+  JAVA_REVEALED,
+}
+
+#[derive(Resource, Default, PartialEq)]
 struct Texter {
+  entity: Option<Entity>,
   time_save: usize,
-  state: State,
+  milestone: Option<Milestone>,
+  milestone_save: usize,
+  glyph_save: usize,
+  // the score's spin, kept here rather than read off the transform so reduced motion only
+  // changes what's drawn and not when the corruption moves on
+  angle: f32,
 }
 
-#[derive(Default, PartialEq)]
+#[derive(Resource, Default, PartialEq)]
 struct Player {
   entity: Option<Entity>,
   i: f32,
@@ -56,8 +198,24 @@ struct Player {
   vel_j: f32,
   scale: f32,
   jump_count: usize,
+  best_jump_count: usize,
   dir: Dir,
   size: Vec2,
+  grounded: bool,
+  jump_held: bool,
+  // rising from a jump of its own, the only rise letting go of jump cuts short
+  jumping: bool,
+  // none until a press or leaving the ground sets them, online frame 0 is tc 0
+  coyote_until: Option<usize>,
+  jump_buffer_until: Option<usize>,
+  air_jumps: usize,
+  max_air_jumps: usize,
+  shield: bool,
+  deflect_scale: f32,
+  health: usize,
+  hurt_until: usize,
+  knock_vel_i: f32,
+  knock_vel_j: f32,
   // state: State,
 }
 
@@ -70,9 +228,10 @@ struct Obj {
   vel_j: f32,
   scale: f32,
   size: Vec2,
+  deflected: bool,
 }
 
-#[derive(Default, PartialEq)]
+#[derive(Resource, Default, PartialEq)]
 struct Java {
   entity: Option<Entity>,
   colour: Color,
@@ -81,6 +240,8 @@ struct Java {
   vel_i: f32,
   vel_j: f32,
   scale: f32,
+  size: Vec2,
+  health: usize,
   dir: Dir,
   time_save: usize,
   obj_vec: Vec<Obj>,
@@ -91,91 +252,271 @@ struct Java {
 #[derive(Component)]
 struct ScoreRotate;
 
+// online play needs the same order on every machine, the multi-threaded executor would otherwise pick one
+#[derive(SystemSet, Clone, Hash, Debug, PartialEq, Eq)]
+enum Sim {
+  INPUT,
+  PHYSICS,
+  COLLISION,
+  RULES,
+  PRESENTATION,
+}
+
+// everything that changes game state runs in FixedUpdate at 60hz, one set after the other, so a
+// tick always sees the same order; Update keeps menus, hud text and other per-frame work
+fn simulation(app: &mut App) {
+  app
+    .insert_resource(Time::<Fixed>::from_hz(60.0))
+    .configure_sets(
+      FixedUpdate,
+      (
+        Sim::INPUT,
+        Sim::PHYSICS,
+        Sim::COLLISION,
+        Sim::RULES,
+        Sim::PRESENTATION,
+      )
+        .chain(),
+    )
+    // a stalled lockstep frame stops everything after the step, or the clients drift apart
+    .configure_sets(
+      FixedUpdate,
+      (Sim::COLLISION, Sim::RULES, Sim::PRESENTATION).run_if(frame_ran),
+    )
+    .add_systems(
+      FixedUpdate,
+      (
+        bot_update.in_set(Sim::INPUT),
+        player_move.in_set(Sim::PHYSICS),
+        (local_versus, java_move, powerup_update)
+          .chain()
+          .in_set(Sim::COLLISION),
+        // each rule sees the phase the one before it left
+        (
+          run_reset,
+          apply_phase,
+          milestone_update,
+          apply_phase,
+          texter_state,
+          apply_phase,
+          score_update,
+          apply_phase,
+          score_corrupt,
+        )
+          .chain()
+          .in_set(Sim::RULES),
+        (tween_update, ghost_update, camera_follow, particle_update).in_set(Sim::PRESENTATION),
+      ),
+    );
+}
+
+fn frame_ran(net: Res<Net>) -> bool {
+  net.ran()
+}
+
+// bevy only applies NextState once a frame, before the fixed ticks, which would leave a phase
+// change to however many ticks that frame happens to run
+fn apply_phase(world: &mut World) {
+  world.run_schedule(StateTransition);
+}
+
 fn main() {
-  App::new()
+  let mut args = std::env::args().skip(1);
+  match args.next().as_deref() {
+    Some("--soak") => {
+      bot::soak(
+        args
+          .next()
+          .and_then(|n| n.parse().ok())
+          .unwrap_or(SOAK_TICKS),
+      );
+      return;
+    }
+    Some("--gym") => {
+      if let Err(e) = gym::serve() {
+        eprintln!("gym: {}", e);
+      }
+      return;
+    }
+    _ => {}
+  }
+
+  let mut app = App::new();
+  app
     .init_resource::<Player>()
+    .init_resource::<Bot>()
+    .init_resource::<Local>()
+    .init_resource::<Net>()
+    .init_resource::<Ghost>()
+    .init_resource::<Speedrun>()
     .init_resource::<Java>()
     .init_resource::<Texter>()
-    .insert_resource(WindowDescriptor {
-      title: "haskellHop".to_string(),
-      width: WINDOW_WIDTH,
-      height: WINDOW_HEIGHT,
-      present_mode: PresentMode::AutoVsync,
+    .init_resource::<PowerUps>()
+    // the headless app keeps the defaults, so demos and training don't depend on a player's saves
+    .insert_resource(saves::load::<Settings>(SETTINGS_PATH).unwrap_or_default())
+    .init_resource::<CameraRig>()
+    .init_resource::<Milestones>()
+    .init_resource::<Particles>()
+    .init_resource::<PostFx>()
+    .init_resource::<Background>()
+    .init_resource::<Tweens>()
+    .init_resource::<Achievements>()
+    .init_resource::<Skins>()
+    .init_resource::<Locale>()
+    .init_resource::<Access>()
+    .add_plugins(DefaultPlugins.set(WindowPlugin {
+      primary_window: Some(Window {
+        title: "haskellHop".to_string(),
+        resolution: (WINDOW_WIDTH as u32, WINDOW_HEIGHT as u32).into(),
+        present_mode: PresentMode::AutoVsync,
+        resizable: true,
+        fit_canvas_to_parent: true,
+        ..default()
+      }),
       ..default()
-    })
-    .add_plugins(DefaultPlugins)
-    .add_startup_system(setup)
-    .add_stage_after(
-      CoreStage::Update,
-      "player_move",
-      SystemStage::parallel()
-        .with_run_criteria(FixedTimestep::step(1.0 / 60.0))
-        .with_system(player_move),
-    )
-    .add_stage_after(
-      CoreStage::Update,
-      "score_update",
-      SystemStage::parallel()
-        .with_run_criteria(FixedTimestep::step(1.0 / 60.0))
-        .with_system(score_update),
+    }))
+    .add_message::<GameEvent>()
+    .add_message::<Burst>()
+    .add_message::<TweenDone>()
+    .init_state::<Phase>()
+    .init_asset::<MilestoneTable>()
+    .register_asset_loader(RonLoader::<MilestoneTable>::new("milestones.ron"))
+    .init_asset::<SplitTable>()
+    .register_asset_loader(RonLoader::<SplitTable>::new("splits.ron"))
+    .init_asset::<SkinTable>()
+    .register_asset_loader(RonLoader::<SkinTable>::new("skins.ron"))
+    .init_asset::<StringTable>()
+    .register_asset_loader(RonLoader::<StringTable>::new("locale.ron"))
+    .add_plugins(Material2dPlugin::<GlitchMaterial>::default())
+    .add_systems(
+      Startup,
+      (
+        locale_setup,
+        camera_setup,
+        postfx_setup,
+        background_setup,
+        setup,
+        ghost_setup,
+        milestone_setup,
+        speedrun_setup,
+        achievement_setup,
+        skin_setup,
+        access_setup,
+        hud_setup,
+      ),
     )
-    .add_system(texter_state)
-    .add_stage_after(
-      CoreStage::Update,
-      "score_corrupt",
-      SystemStage::parallel()
-        .with_run_criteria(FixedTimestep::step(1.0 / 2.0))
-        .with_system(score_corrupt),
+    .add_systems(
+      Update,
+      (
+        hud_update,
+        speedrun_update,
+        achievement_update,
+        achievement_ui,
+        skin_update,
+        locale_update,
+        access_update,
+        camera_letterbox,
+        particle_emit,
+        postfx_toggle,
+        postfx_resize,
+        postfx_update,
+        background_update,
+        text_fx_update,
+        window_controls,
+        local_mode,
+        net_lobby,
+      ),
     )
-    .add_stage_after(
-      CoreStage::Update,
-      "java_move",
-      SystemStage::parallel()
-        .with_run_criteria(FixedTimestep::step(1.0 / 60.0))
-        .with_system(java_move),
-    )
-    .insert_resource(ClearColor(BACKGROUND_COLOR))
-    .run();
+    .insert_resource(ClearColor(BACKGROUND_COLOR));
+  app
+    .sub_app_mut(RenderApp)
+    .add_systems(ExtractSchedule, postfx_watch);
+  simulation(&mut app);
+  // after the plugins, so it wraps the hook the log plugin installs
+  crash::install();
+  app.run();
 }
 
 fn texter_state(
   mut player: ResMut<Player>,
+  local: Res<Local>,
+  net: Res<Net>,
+  mut java: ResMut<Java>,
   time: Res<Time>,
   mut texter: ResMut<Texter>,
-
-  mut transform_q: Query<&mut Transform>,
+  phase: Res<State<Phase>>,
+  mut next_phase: ResMut<NextState<Phase>>,
+  mut tweens: ResMut<Tweens>,
+  background_colour: Res<ClearColor>,
+  mut tweens_done: MessageReader<TweenDone>,
+  mut events: MessageWriter<GameEvent>,
 ) {
-  if player.jump_count == 69 {
-    if texter.state != State::NICE {
-      texter.state = State::NICE;
-      texter.time_save = time.time_since_startup().as_millis() as usize;
-    }
-  } else if texter.state == State::NICE &&
-    time.time_since_startup().as_millis() as usize - texter.time_save >= 1000
-  {
-    texter.state = State::DEFAULT;
+  let tc = net.clock(time.elapsed().as_millis() as usize);
+  let mut state = *phase.get();
+  let milestone_ms = texter.milestone.as_ref().map_or(0, |m| m.duration_ms);
+  if state == Phase::MILESTONE && tc.saturating_sub(texter.milestone_save) >= milestone_ms {
+    state = Phase::DEFAULT;
   }
 
-  if player.jump_count >= CORRUPT_JUMP && texter.state == State::DEFAULT {
-    texter.state = State::CORRUPT_A;
-    texter.time_save = time.time_since_startup().as_millis() as usize;
+  let jumps = local.team_jumps(&player);
+  player.best_jump_count = player.best_jump_count.max(jumps);
+
+  if jumps >= CORRUPT_JUMP && state == Phase::DEFAULT {
+    state = Phase::CORRUPT_A;
+    texter.time_save = tc;
+    tweens.start(
+      texter.time_save,
+      Tween {
+        entity: None,
+        target: TweenTarget::CLEAR_COLOUR(background_colour.0, darken(BACKGROUND_COLOR, 0.3)),
+        ease: Ease::IN_QUAD,
+        duration_ms: CORRUPT_A_FADE_MS,
+        tag: Some(TweenTag::BACKGROUND_FADE),
+      },
+    );
+    events.write(GameEvent::CORRUPT_A);
+  }
+
+  for done in tweens_done.read() {
+    match done.tag {
+      TweenTag::SCORE_FADE_OUT if state == Phase::CORRUPT_B => {
+        java.time_save = tc;
+        state = Phase::JAVA;
+        events.write(GameEvent::JAVA);
+      }
+      TweenTag::JAVA_FADE_IN => {
+        events.write(GameEvent::JAVA_REVEALED);
+      }
+      _ => {}
+    }
+  }
+  if state != *phase.get() {
+    next_phase.set(state);
   }
 }
 
 fn java_move(
   mut java: ResMut<Java>,
   mut player: ResMut<Player>,
-  mut texter: ResMut<Texter>,
+  mut local: ResMut<Local>,
+  net: Res<Net>,
+  phase: Res<State<Phase>>,
+  mut rig: ResMut<CameraRig>,
+  powerups: Res<PowerUps>,
   time: Res<Time>,
   mut transform_q: Query<&mut Transform>,
   mut commands: Commands,
   asset_server: Res<AssetServer>,
+  mut events: MessageWriter<GameEvent>,
+  mut bursts: MessageWriter<Burst>,
 ) {
-  if texter.state == State::JAVA {
-    let mut rng = thread_rng();
-    let tc = time.time_since_startup().as_millis() as usize;
+  if *phase.get() == Phase::JAVA {
+    let time_scale = powerups.time_scale();
+    let tc = net.clock(time.elapsed().as_millis() as usize);
+    let mut rng = net.rng(tc);
+    let mut thrown = None;
     if tc - java.time_save >= 2000 {
-      java.vel_j = vec![-5.0, 5.0].choose(&mut rng).unwrap() * 1.0;
+      java.vel_j = *[-5.0, 5.0].choose(&mut rng).unwrap();
       java.time_save = tc;
       let obj_scale = java.scale / 5.0;
       let obj_i = java.i;
@@ -188,135 +529,442 @@ fn java_move(
         vel_i: -1.0,
         vel_j: 0.0,
         scale: obj_scale,
+        deflected: false,
         entity: Some(
           commands
-            .spawn_bundle(SpriteBundle {
-              texture: asset_server.load("textures/apple.png"),
-              transform: Transform {
-                scale: Vec3::new(obj_scale, obj_scale, 0.0),
-                translation: Vec3::new(obj_j, obj_i, 0.0),
-                ..default()
-              },
-              sprite: Sprite {
+            .spawn((
+              Sprite {
+                image: asset_server.load("textures/apple.png"),
                 flip_x: true,
                 flip_y: false,
                 ..default()
               },
-              ..default()
-            })
+              Transform {
+                scale: Vec3::new(obj_scale, obj_scale, 0.0),
+                translation: Vec3::new(obj_j, obj_i, 0.0),
+                ..default()
+              },
+            ))
             .id(),
         ),
       };
-      java.obj_vec.push(obj);
+      thrown = Some(obj);
     }
 
-    for obj in &mut java.obj_vec {
-      if transform_q.get_mut(obj.entity.unwrap()).is_ok() {
-        let obj_tl = transform_q
-            .get_mut(obj.entity.unwrap())
-            .unwrap()
-            .translation;
-        let player_tl = transform_q
-            .get_mut(player.entity.unwrap())
-            .unwrap()
-            .translation;
-        match collide(
-          player_tl,
-          player.size,
-          obj_tl,
-          obj.size,
-        ) {
-          Some(_col) => {
-            obj.vel_i = 15.0;
-            obj.vel_j = (obj_tl.x - player_tl.x) / 10.0;
-          }
-          None => {}
-        };
+    let java_box = Aabb2d::new(Vec2::new(java.j, java.i), java.size / 2.0);
+    let mut java_hits = 0;
+    let mut players = vec![&mut *player];
+    if local.is_active() {
+      players.push(&mut local.player);
+    }
 
-        obj.i += obj.vel_i;
-        obj.j += obj.vel_j;
-        obj.vel_i -= GRAVITY / 40.0;
-        *transform_q.get_mut(obj.entity.unwrap()).unwrap() = Transform {
-          translation: Vec3::new(obj.j, obj.i, 0.0),
-          scale: Vec3::new(obj.scale, obj.scale, 0.0),
-          ..default()
-        };
+    java.obj_vec.retain_mut(|obj| {
+      let mut transform = match lookup(&mut transform_q, obj.entity, "apple") {
+        Some(transform) => transform,
+        None => return false,
+      };
+      let obj_tl = transform.translation;
+      // an apple can only hit one player per tick
+      for player in players.iter_mut() {
+        let player_tl = Vec3::new(player.j, player.i, 0.0);
+        let player_box = Aabb2d::new(player_tl.xy(), player.size * player.deflect_scale / 2.0);
+        if !player_box.intersects(&Aabb2d::new(obj_tl.xy(), obj.size / 2.0)) {
+          continue;
+        }
+
+        if player.vel_i <= 0.0 && obj.vel_i < 0.0 && tc >= player.hurt_until && !player.shield {
+          player.health = player.health.saturating_sub(1);
+          player.hurt_until = tc + INVINCIBLE_MS;
+          player.knock_vel_i = KNOCKBACK_VEL / 2.0;
+          player.knock_vel_j = (player_tl.x - obj_tl.x).signum() * KNOCKBACK_VEL;
+          rig.add_trauma(0.5);
+          events.write(GameEvent::HURT);
+        } else if !obj.deflected {
+          events.write(GameEvent::DEFLECT);
+          bursts.write(Burst {
+            kind: BurstKind::SPARK,
+            i: obj_tl.y,
+            j: obj_tl.x,
+          });
+        }
+        obj.vel_i = 15.0;
+        obj.vel_j = (obj_tl.x - player_tl.x) / 10.0;
+        obj.deflected = true;
+        break;
       }
-    }
 
-    if java.j <= -(WINDOW_WIDTH / 2.0) + (WINDOW_WIDTH * 0.1) {
+      if obj.deflected && java_box.intersects(&Aabb2d::new(obj_tl.xy(), obj.size / 2.0)) {
+        java_hits += 1;
+        events.write(GameEvent::JAVA_HIT);
+        despawn(&mut commands, obj.entity);
+        return false;
+      }
+
+      obj.i += obj.vel_i * time_scale;
+      obj.j += obj.vel_j * time_scale;
+      obj.vel_i -= GRAVITY / 40.0 * time_scale;
+      *transform = Transform {
+        translation: Vec3::new(obj.j, obj.i, 0.0),
+        scale: Vec3::new(obj.scale, obj.scale, 0.0),
+        ..default()
+      };
+
+      // missed apples would otherwise fall forever and pile up in obj_vec
+      if obj.i < -LEVEL_HEIGHT {
+        despawn(&mut commands, obj.entity);
+        return false;
+      }
+      true
+    });
+    java.health = java.health.saturating_sub(java_hits);
+    // its entity only exists once the commands are applied, so the lookup above would drop it
+    java.obj_vec.extend(thrown);
+
+    if java.j <= rig.j - (WINDOW_WIDTH / 2.0) + (WINDOW_WIDTH * 0.1) {
       java.vel_j = java.vel_j.abs();
-    } else if java.j >= (WINDOW_WIDTH / 2.0) - (WINDOW_WIDTH * 0.1) {
+    } else if java.j >= rig.j + (WINDOW_WIDTH / 2.0) - (WINDOW_WIDTH * 0.1) {
       java.vel_j = -(java.vel_j.abs());
     }
 
-    if texter.state == State::JAVA {
-      java.j += java.vel_j;
-      *transform_q.get_mut(java.entity.unwrap()).unwrap() = Transform {
-        translation: Vec3::new(java.j, java.i, 0.0),
+    if *phase.get() == Phase::JAVA {
+      java.j += java.vel_j * time_scale;
+      if let Some(mut transform) = lookup(&mut transform_q, java.entity, "java") {
+        *transform = Transform {
+          translation: Vec3::new(java.j, java.i, 0.0),
 
-        scale: Vec3::new(java.scale, java.scale, 0.0),
-        ..default()
-      };
+          scale: Vec3::new(java.scale, java.scale, 0.0),
+          ..default()
+        };
+      }
     }
   }
 }
 
 fn player_move(
   time: Res<Time>,
-  keyboard_input: Res<Input<KeyCode>>,
+  keyboard_input: Res<ButtonInput<KeyCode>>,
+  gamepad_q: Query<&Gamepad>,
+  settings: Res<Settings>,
+  bot: Res<Bot>,
+  skins: Res<Skins>,
+  access: Res<Access>,
   mut player: ResMut<Player>,
+  mut local: ResMut<Local>,
+  mut net: ResMut<Net>,
   mut transform_q: Query<&mut Transform>,
   mut sprite_q: Query<&mut Sprite>,
+  mut events: MessageWriter<GameEvent>,
+  mut bursts: MessageWriter<Burst>,
 ) {
   // let td = time.delta().as_millis() as f32 / 60.0;
+  let tc = net.clock(time.elapsed().as_millis() as usize);
+  let controls = if bot.active {
+    bot.controls
+  } else {
+    local::controls(0, &keyboard_input, &gamepad_q)
+  };
 
-  if (keyboard_input.pressed(KeyCode::W) || keyboard_input.pressed(KeyCode::Space)) &&
-    player.vel_i == 0.0
-  {
+  if net.is_online() {
+    // the other machine's player is the second player here, both step on the slot inputs
+    let inputs = match net.step(controls) {
+      Some(inputs) => inputs,
+      None => return,
+    };
+    let slot = net.slot;
+    let settings = settings.with_assists(net.assists);
+    move_player(
+      &mut player,
+      inputs[slot],
+      &settings,
+      tc,
+      &mut events,
+      &mut bursts,
+    );
+    move_player(
+      &mut local.player,
+      inputs[1 - slot],
+      &settings,
+      tc,
+      &mut events,
+      &mut bursts,
+    );
+    draw_player(
+      &player,
+      skins.tint,
+      tc,
+      &access,
+      &mut transform_q,
+      &mut sprite_q,
+    );
+    draw_player(
+      &local.player,
+      css::ORANGE,
+      tc,
+      &access,
+      &mut transform_q,
+      &mut sprite_q,
+    );
+    return;
+  }
+
+  move_player(
+    &mut player,
+    controls,
+    &settings,
+    tc,
+    &mut events,
+    &mut bursts,
+  );
+  draw_player(
+    &player,
+    skins.tint,
+    tc,
+    &access,
+    &mut transform_q,
+    &mut sprite_q,
+  );
+
+  if local.is_active() {
+    let controls = local::controls(1, &keyboard_input, &gamepad_q);
+    move_player(
+      &mut local.player,
+      controls,
+      &settings,
+      tc,
+      &mut events,
+      &mut bursts,
+    );
+    draw_player(
+      &local.player,
+      css::ORANGE,
+      tc,
+      &access,
+      &mut transform_q,
+      &mut sprite_q,
+    );
+  }
+}
+
+fn move_player(
+  player: &mut Player,
+  controls: Controls,
+  settings: &Settings,
+  tc: usize,
+  events: &mut MessageWriter<GameEvent>,
+  bursts: &mut MessageWriter<Burst>,
+) {
+  let floor = -(WINDOW_HEIGHT / 2.0) + (WINDOW_HEIGHT * 0.1);
+  let wall = (LEVEL_WIDTH / 2.0) - (player.size.x / 2.0);
+
+  // edge detect here instead of just_pressed, the fixed timestep can skip or repeat frames
+  let jump_pressed = controls.jump;
+  let jump_edge = jump_pressed && !player.jump_held;
+  player.jump_held = jump_pressed;
+
+  if jump_edge || (settings.auto_hop && jump_pressed) {
+    player.jump_buffer_until = Some(tc + JUMP_BUFFER_MS);
+  }
+
+  let max_air_jumps = player.max_air_jumps + settings.double_jump as usize;
+  player.air_jumps = player.air_jumps.min(max_air_jumps);
+
+  let buffered = player.jump_buffer_until.is_some_and(|until| tc <= until);
+  let coyote = player.coyote_until.is_some_and(|until| tc <= until);
+  if buffered && (player.grounded || coyote) {
+    player.jump_buffer_until = None;
+    player.coyote_until = None;
+    player.grounded = false;
     player.jump_count += 1;
-    player.vel_i = 10.0;
+    events.write(GameEvent::JUMP);
+    player.vel_i = JUMP_VEL;
+    player.jumping = true;
+  } else if jump_edge && settings.wall_jump && !player.grounded && player.j.abs() >= wall {
+    player.jump_buffer_until = None;
+    player.jump_count += 1;
+    events.write(GameEvent::JUMP);
+    player.vel_i = JUMP_VEL;
+    player.jumping = true;
+    player.vel_j = -player.j.signum() * WALL_JUMP_VEL;
+    player.dir = if player.j > 0.0 {
+      Dir::LEFT
+    } else {
+      Dir::RIGHT
+    };
+  } else if jump_edge && !player.grounded && player.air_jumps > 0 {
+    player.jump_buffer_until = None;
+    player.air_jumps -= 1;
+    player.jump_count += 1;
+    events.write(GameEvent::JUMP);
+    player.vel_i = JUMP_VEL;
+    player.jumping = true;
+  }
+
+  // stomp bounces and knockback keep their full height
+  if player.vel_i <= 0.0 {
+    player.jumping = false;
+  }
+  if player.jumping && !jump_pressed && player.vel_i > JUMP_RELEASE_VEL {
+    player.vel_i = JUMP_RELEASE_VEL;
+  }
+
+  if player.knock_vel_i > 0.0 {
+    player.jumping = false;
   }
+  player.vel_i += player.knock_vel_i;
+  player.vel_j += player.knock_vel_j;
+  player.knock_vel_i = 0.0;
+  player.knock_vel_j = 0.0;
 
-  if keyboard_input.pressed(KeyCode::D) {
+  if controls.right {
     player.vel_j += 0.4;
     player.dir = Dir::RIGHT;
   }
 
-  if keyboard_input.pressed(KeyCode::A) {
+  if controls.left {
     player.vel_j -= 0.4;
     player.dir = Dir::LEFT;
   }
 
-  let floor = -(WINDOW_HEIGHT / 2.0) + (WINDOW_HEIGHT * 0.1);
-
   player.j += player.vel_j;
 
-  if player.i + player.vel_i < floor {
+  if player.j.abs() > wall {
+    player.j = player.j.signum() * wall;
+    player.vel_j = 0.0;
+  }
+
+  if player.i + player.vel_i <= floor {
+    if !player.grounded {
+      events.write(GameEvent::LAND);
+      bursts.write(Burst {
+        kind: BurstKind::DUST,
+        i: floor - (player.size.y / 2.0),
+        j: player.j,
+      });
+    }
     player.i = floor;
     player.vel_i = 0.0;
+    player.grounded = true;
+    player.coyote_until = Some(tc + COYOTE_MS);
+    player.air_jumps = max_air_jumps;
 
-    player.vel_j = player.vel_j * FRICTION;
+    player.vel_j *= FRICTION;
   } else {
-    player.i = player.i + player.vel_i;
+    player.grounded = false;
+    player.i += player.vel_i;
     player.vel_i -= GRAVITY / 15.0;
   }
+}
 
-  *transform_q.get_mut(player.entity.unwrap()).unwrap() = Transform {
-    translation: Vec3::new(player.j, player.i, 0.0),
-    scale: Vec3::new(player.scale, player.scale, 0.0),
+fn draw_player(
+  player: &Player,
+  tint: Srgba,
+  tc: usize,
+  access: &Access,
+  transform_q: &mut Query<&mut Transform>,
+  sprite_q: &mut Query<&mut Sprite>,
+) {
+  if let Some(mut transform) = lookup(transform_q, player.entity, "player") {
+    *transform = Transform {
+      translation: Vec3::new(player.j, player.i, 0.0),
+      scale: Vec3::new(player.scale, player.scale, 0.0),
 
-    ..default()
-  };
+      ..default()
+    };
+  }
 
-  *sprite_q.get_mut(player.entity.unwrap()).unwrap() = Sprite {
-    flip_x: match player.dir {
-      Dir::LEFT => true,
-      Dir::RIGHT => false,
-    },
-    flip_y: false,
-    ..default()
+  let mut sprite = match lookup(sprite_q, player.entity, "player") {
+    Some(sprite) => sprite,
+    None => return,
   };
+  // the sprite holds its image now, so only the fields drawn here are touched
+  sprite.flip_x = match player.dir {
+    Dir::LEFT => true,
+    Dir::RIGHT => false,
+  };
+  sprite.flip_y = false;
+  sprite.color = Color::srgba(
+    if player.shield { 0.0 } else { tint.red },
+    tint.green,
+    tint.blue,
+    if tc >= player.hurt_until {
+      1.0
+    } else if access.reduce_flashing {
+      // a steady fade says hurt without the strobe
+      0.5
+    } else if (tc / BLINK_MS).is_multiple_of(2) {
+      0.2
+    } else {
+      1.0
+    },
+  );
+}
+
+fn run_reset(
+  clock: Clock,
+  mut player: ResMut<Player>,
+  mut local: ResMut<Local>,
+  mut java: ResMut<Java>,
+  phase: Res<State<Phase>>,
+  mut next_phase: ResMut<NextState<Phase>>,
+  mut tweens: ResMut<Tweens>,
+  mut powerups: ResMut<PowerUps>,
+  mut background_colour: ResMut<ClearColor>,
+  mut commands: Commands,
+  mut events: MessageWriter<GameEvent>,
+) {
+  let dead = player.health == 0 || (local.is_active() && local.player.health == 0);
+  let versus_won = local.mode == Mode::VERSUS &&
+    (dead || player.jump_count.max(local.player.jump_count) >= VERSUS_JUMPS);
+
+  if local.restart || dead || versus_won || (*phase.get() == Phase::JAVA && java.health == 0) {
+    if versus_won && !local.restart {
+      // whoever is still standing wins, otherwise whoever got to the jump target
+      let first = if dead {
+        local.player.health == 0
+      } else {
+        player.jump_count >= local.player.jump_count
+      };
+      local.winner = Some((if first { 1 } else { 2 }, clock.now()));
+    }
+    if dead && !local.restart {
+      events.write(GameEvent::DEATH);
+    } else if *phase.get() == Phase::JAVA && java.health == 0 {
+      events.write(GameEvent::JAVA_DEFEATED);
+    }
+    local.restart = false;
+
+    for player in [&mut *player, &mut local.player] {
+      player.health = PLAYER_HEALTH;
+      player.hurt_until = 0;
+      player.jump_count = 0;
+    }
+
+    reset_java(&mut java, &mut commands);
+    powerups.clear(&mut commands);
+
+    for tag in [
+      TweenTag::BACKGROUND_FADE,
+      TweenTag::SCORE_FADE_OUT,
+      TweenTag::JAVA_FADE_IN,
+    ] {
+      tweens.cancel(tag);
+    }
+    NextState::set_if_neq(&mut next_phase, Phase::DEFAULT);
+    *background_colour = ClearColor(BACKGROUND_COLOR);
+  }
+}
+
+// back to before the corruption, java and its apples gone until the next one
+fn reset_java(java: &mut Java, commands: &mut Commands) {
+  for obj in java.obj_vec.drain(..) {
+    despawn(commands, obj.entity);
+  }
+  despawn(commands, java.entity.take());
+  java.colour = Color::srgba(1.0, 1.0, 1.0, 0.0);
+  java.j = 0.0;
+  java.vel_j = 0.0;
+  java.health = JAVA_HEALTH;
+  java.time_save = 0;
 }
 
 fn setup(
@@ -324,26 +972,32 @@ fn setup(
   asset_server: Res<AssetServer>,
   mut player: ResMut<Player>,
   mut java: ResMut<Java>,
+  mut texter: ResMut<Texter>,
 ) {
-  commands.spawn_bundle(Camera2dBundle::default());
   let font = asset_server.load("fonts/Monocraft.ttf");
-  let text_style = TextStyle {
-    font,
-    font_size: 60.0,
-    color: Color::WHITE,
-  };
-  let text_alignment = TextAlignment::CENTER;
 
   player.jump_count = 0;
   player.size = Vec2::new(100.0, 100.0);
+  player.deflect_scale = 1.0;
 
-  commands
-    .spawn_bundle(Text2dBundle {
-      text: Text::from_section(player.jump_count.to_string().as_str(), text_style.clone())
-        .with_alignment(text_alignment),
-      ..default()
-    })
-    .insert(ScoreRotate);
+  texter.entity = Some(
+    commands
+      .spawn((
+        Text2d::default(),
+        TextFont {
+          font,
+          font_size: 60.0,
+          ..default()
+        },
+        TextColor(Color::WHITE),
+        TextLayout::new_with_justify(Justify::Center),
+        ScoreRotate,
+        TextFx::new(vec![]),
+      ))
+      .id(),
+  );
+
+  player.health = PLAYER_HEALTH;
 
   player.i = -(WINDOW_HEIGHT / 2.0) + (WINDOW_HEIGHT * 0.1);
   player.j = -(WINDOW_WIDTH / 2.0) + (WINDOW_HEIGHT * 0.1);
@@ -353,189 +1007,288 @@ fn setup(
 
   player.entity = Some(
     commands
-      .spawn_bundle(SpriteBundle {
-        texture: asset_server.load("textures/haskell.png"),
-        transform: Transform {
-          scale: Vec3::new(player.scale, player.scale, 0.0),
-          translation: Vec3::new(player.j, player.i, 0.0),
-          ..default()
-        },
-        sprite: Sprite {
+      .spawn((
+        Sprite {
+          image: asset_server.load("textures/haskell.png"),
           flip_x: true,
           flip_y: false,
           ..default()
         },
-        ..default()
-      })
+        Transform {
+          scale: Vec3::new(player.scale, player.scale, 0.0),
+          translation: Vec3::new(player.j, player.i, 0.0),
+          ..default()
+        },
+      ))
       .id(),
   );
 
+  for n in 0..=(LEVEL_WIDTH / LEVEL_MARKER_GAP) as usize {
+    commands.spawn((
+      Sprite::from_color(Color::srgba(0.0, 0.0, 0.0, 0.3), Vec2::new(10.0, 20.0)),
+      Transform::from_xyz(
+        -(LEVEL_WIDTH / 2.0) + n as f32 * LEVEL_MARKER_GAP,
+        -(WINDOW_HEIGHT / 2.0) + 10.0,
+        0.0,
+      ),
+    ));
+  }
+
   java.entity = None;
-  java.colour = Color::rgba(1.0, 1.0, 1.0, 0.0);
+  java.colour = Color::srgba(1.0, 1.0, 1.0, 0.0);
   java.i = (WINDOW_HEIGHT / 2.0) - (WINDOW_HEIGHT * 0.1);
   java.j = 0.0;
   java.vel_i = 0.0;
   java.vel_j = 0.0;
   java.scale = PLAYER_SCALE;
+  java.size = Vec2::new(100.0, 100.0);
+  java.health = JAVA_HEALTH;
 }
 
 fn score_update(
-  time: Res<Time>,
-  mut transform_q: Query<&mut Transform, (With<Text>, With<ScoreRotate>)>,
-  mut text_q: Query<&mut Text>,
-  mut sprite_q: Query<&mut Sprite>,
+  clock: Clock,
+  mut transform_q: Query<&mut Transform, (With<Text2d>, With<ScoreRotate>)>,
+  mut text_q: Query<(&mut TextColor, &mut TextFx), With<ScoreRotate>>,
   mut texter: ResMut<Texter>,
-  mut player: ResMut<Player>,
+  access: Res<Access>,
+  phase: Res<State<Phase>>,
+  mut next_phase: ResMut<NextState<Phase>>,
+  player: Res<Player>,
+  local: Res<Local>,
   mut java: ResMut<Java>,
+  mut tweens: ResMut<Tweens>,
   mut commands: Commands,
   asset_server: Res<AssetServer>,
-  mut background_colour: ResMut<ClearColor>,
+  background_colour: Res<ClearColor>,
+  rig: Res<CameraRig>,
+  mut events: MessageWriter<GameEvent>,
 ) {
   let mut rot = 5_f32;
+  let tc = clock.now();
   // let td = time.delta().as_millis() as f32 / 60.0;
-  for mut text in &mut text_q {
-    match texter.state {
-      State::DEFAULT => {
-        text.sections[0].value = player.jump_count.to_string();
-        text.sections[0].style.color = Color::WHITE;
+  for (mut colour, mut fx) in &mut text_q {
+    fx.anchor = Some(Vec3::new(rig.j, rig.i, 0.0));
+    match phase.get() {
+      Phase::DEFAULT => {
+        fx.set(tc, local.score(&player));
+        fx.effect_vec = vec![TextEffect::POP {
+          scale: 1.4,
+          ms: 200,
+        }];
+        fx.palette.clear();
+        colour.0 = Color::WHITE;
       }
-      State::NICE => {
-        text.sections[0].value = "haha funny number".to_string();
-        text.sections[0].style.color = Color::CYAN;
+      Phase::MILESTONE => {
+        if let Some(milestone) = &texter.milestone {
+          let (r, g, b) = milestone.colour;
+          fx.set(tc, milestone.message.clone());
+          fx.effect_vec = vec![
+            TextEffect::TYPEWRITER { ms_per_char: 40 },
+            TextEffect::WAVE {
+              amplitude: 0.15,
+              speed: 8.0,
+            },
+          ];
+          fx.effect_vec.extend(match milestone.animation {
+            Animation::POP => Some(TextEffect::POP {
+              scale: 1.5,
+              ms: milestone.duration_ms,
+            }),
+            Animation::SHAKE => Some(TextEffect::SHAKE(5.0)),
+            _ => None,
+          });
+          fx.palette.clear();
+          colour.0 = Color::srgb(r, g, b);
+          if milestone.animation == Animation::SPIN {
+            rot = 20_f32;
+          }
+        }
       }
 
-      State::CORRUPT_A => {
-        *background_colour = ClearColor(Color::rgb(
-          background_colour.r() * 0.99,
-          background_colour.b() * 0.99,
-          background_colour.g() * 0.99,
-        ));
-        text.sections[0].value = player.jump_count.to_string();
-        text.sections[0].style.color = Color::WHITE;
+      Phase::CORRUPT_A => {
+        fx.set(tc, local.score(&player));
+        fx.effect_vec = vec![TextEffect::SHAKE(2.0)];
+        colour.0 = Color::WHITE;
         rot = 2_f32;
       }
 
-      State::CORRUPT_B => {
+      Phase::CORRUPT_B => {
         rot = 0_f32;
-        *background_colour = ClearColor(Color::rgb(
-          background_colour.r() * 0.99,
-          background_colour.b() * 0.99,
-          background_colour.g() * 0.99,
-        ));
-
-        let tc = text.sections[0].style.color;
-        text.sections[0].style.color = Color::rgba(tc.r(), tc.g(), tc.b(), tc.a() * 0.98);
 
         if java.entity.is_none() {
-          for mut transform in &mut transform_q {
-            transform.rotation = Quat::from_rotation_z(0.0_f32.to_radians());
-          }
+          texter.angle = 0.0;
           // let mut bc = background_colour.clone();
           // *background_colour = ClearColor(Color::BLACK);
+          java.j = rig.j;
+          // drops in from above and grows into place while the score fades
+          let above = Vec3::new(java.j, java.i + WINDOW_HEIGHT / 2.0, 0.0);
+          let home = Vec3::new(java.j, java.i, 0.0);
+          let size = Vec3::new(java.scale, java.scale, 0.0);
           java.entity = Some(
             commands
-              .spawn_bundle(SpriteBundle {
-                texture: asset_server.load("textures/java.png"),
-                transform: Transform {
-                  scale: Vec3::new(java.scale, java.scale, 0.0),
-                  translation: Vec3::new(java.j, java.i, 0.0),
-                  ..default()
-                },
-                sprite: Sprite {
+              .spawn((
+                Sprite {
+                  image: asset_server.load("textures/java.png"),
                   flip_x: true,
                   flip_y: false,
                   color: java.colour,
                   ..default()
                 },
-                ..default()
-              })
+                Transform {
+                  scale: Vec3::ZERO,
+                  translation: above,
+                  ..default()
+                },
+              ))
               .id(),
           );
-        }
-
-        if sprite_q.get_mut(java.entity.unwrap()).is_ok() {
-          *background_colour = ClearColor(Color::rgb(
-            background_colour.r() * 0.99,
-            background_colour.b() * 0.99,
-            background_colour.g() * 0.99,
-          ));
+          tweens.start(
+            tc,
+            Tween {
+              entity: java.entity,
+              target: TweenTarget::TRANSLATION(above, home),
+              ease: Ease::IN_OUT_QUAD,
+              duration_ms: JAVA_DROP_MS,
+              tag: None,
+            },
+          );
+          tweens.start(
+            tc,
+            Tween {
+              entity: java.entity,
+              target: TweenTarget::SCALE(Vec3::ZERO, size),
+              ease: Ease::OUT_BACK,
+              duration_ms: JAVA_DROP_MS,
+              tag: None,
+            },
+          );
           let jc = java.colour;
-          java.colour = Color::rgba(jc.r(), jc.g(), jc.b(), jc.a() + 0.001);
-          *sprite_q.get_mut(java.entity.unwrap()).unwrap() = Sprite {
-            color: java.colour,
-            ..default()
-          };
+          tweens.start(
+            tc,
+            Tween {
+              entity: java.entity,
+              target: TweenTarget::SPRITE_COLOUR(jc, jc.with_alpha(1.0)),
+              ease: Ease::LINEAR,
+              duration_ms: JAVA_FADE_IN_MS,
+              tag: Some(TweenTag::JAVA_FADE_IN),
+            },
+          );
         }
       }
 
-      State::JAVA => {
-        let jc = java.colour;
-        java.colour = Color::rgba(jc.r(), jc.g(), jc.b(), jc.a() + 0.001);
-        *sprite_q.get_mut(java.entity.unwrap()).unwrap() = Sprite {
-          color: java.colour,
-          ..default()
-        };
-      }
+      Phase::JAVA => {}
     }
   }
 
+  texter.angle = (texter.angle + rot).rem_euclid(360.0);
+  let r = texter.angle;
   for mut transform in &mut transform_q {
-    transform.rotate_z(rot.to_radians());
-    let r = transform.rotation.to_axis_angle().1.to_degrees();
-    if texter.state == State::CORRUPT_A &&
-      (r <= 10.0 || 360.0 - r <= 10.0) &&
-      time.time_since_startup().as_millis() as usize - texter.time_save >= 2000
-    {
-      for mut text in &mut text_q {
-        texter.state = State::CORRUPT_B;
-        let mut rng = thread_rng();
-        let c = Color::GREEN;
-        let tc = text.sections[0].style.color;
-
-        text.sections[0].value = (0..5)
-          .map(|_| {
-            ((33..=126)
-              .into_iter()
-              .collect::<Vec<_>>()
-              .choose(&mut rng)
-              .unwrap() *
-              1) as u8 as char
-          })
-          .collect::<String>();
-        let c = Color::GREEN;
-        let tan = text.sections[0].style.color.a();
-        text.sections[0].style.color = Color::rgba(c.r(), c.g(), c.b(), tan);
-      }
+    transform.rotation = if access.reduced_motion {
+      Quat::IDENTITY
+    } else {
+      Quat::from_rotation_z(r.to_radians())
+    };
+  }
+  if *phase.get() == Phase::CORRUPT_A &&
+    (r <= 10.0 || 360.0 - r <= 10.0) &&
+    tc - texter.time_save >= 2000
+  {
+    events.write(GameEvent::CORRUPT_B);
+    for (mut colour, mut fx) in &mut text_q {
+      next_phase.set(Phase::CORRUPT_B);
+      let mut rng = thread_rng();
+
+      fx.set(tc, glyphs(5, &mut rng));
+      fx.effect_vec = vec![TextEffect::SCRAMBLE { ms: 300 }];
+      fx.palette = vec![
+        css::LIME.into(),
+        css::LIMEGREEN.into(),
+        css::SEA_GREEN.into(),
+      ];
+      let tan = colour.0.alpha();
+      colour.0 = css::LIME.with_alpha(tan).into();
     }
+
+    tweens.start(
+      tc,
+      Tween {
+        entity: None,
+        target: TweenTarget::CLEAR_COLOUR(background_colour.0, Color::BLACK),
+        ease: Ease::OUT_QUAD,
+        duration_ms: CORRUPT_B_FADE_MS,
+        tag: Some(TweenTag::BACKGROUND_FADE),
+      },
+    );
+    tweens.start(
+      tc,
+      Tween {
+        entity: texter.entity,
+        target: TweenTarget::TEXT_COLOUR(css::LIME.into(), Color::srgba(0.0, 1.0, 0.0, 0.0)),
+        ease: Ease::IN_QUAD,
+        duration_ms: CORRUPT_B_FADE_MS,
+        tag: Some(TweenTag::SCORE_FADE_OUT),
+      },
+    );
   }
 }
 
 fn score_corrupt(
-  mut java: ResMut<Java>,
-  mut transform_q: Query<&mut Transform, (With<Text>, With<ScoreRotate>)>,
+  clock: Clock,
   mut texter: ResMut<Texter>,
-  time: Res<Time>,
-  mut text_q: Query<&mut Text>,
+  phase: Res<State<Phase>>,
+  access: Res<Access>,
+  mut fx_q: Query<&mut TextFx, With<ScoreRotate>>,
 ) {
-  if texter.state == State::CORRUPT_B {
-    for mut text in &mut text_q {
+  let tc = clock.now();
+  if *phase.get() == Phase::CORRUPT_B &&
+    !access.reduce_flashing &&
+    tc - texter.glyph_save >= GLYPH_MS
+  {
+    texter.glyph_save = tc;
+    for mut fx in &mut fx_q {
       let mut rng = thread_rng();
 
-      text.sections[0].value = (0..5)
-        .map(|_| {
-          ((33..=126)
-            .into_iter()
-            .collect::<Vec<_>>()
-            .choose(&mut rng)
-            .unwrap() *
-            1) as u8 as char
-        })
-        .collect::<String>();
-
-      if text.sections[0].style.color.a() <= 0.001 {
-        java.time_save = time.time_since_startup().as_millis() as usize;
-        texter.state = State::JAVA;
-      }
+      fx.set(tc, glyphs(5, &mut rng));
     }
   }
 }
+
+// entities can go missing between systems when a despawn or reset lands first, so lookups
+// log and skip instead of taking the whole game down
+fn lookup<'a, T: Component<Mutability = Mutable>>(
+  query: &'a mut Query<&mut T>,
+  entity: Option<Entity>,
+  what: &str,
+) -> Option<Mut<'a, T>> {
+  match entity.map(|entity| query.get_mut(entity)) {
+    Some(Ok(component)) => Some(component),
+    Some(Err(e)) => {
+      warn!("skipping {}: {:?}", what, e);
+      None
+    }
+    None => {
+      warn!("skipping {}: never spawned", what);
+      None
+    }
+  }
+}
+
+// the despawn lands later with the other commands, by then the entity may already be gone,
+// which only warns
+fn despawn(commands: &mut Commands, entity: Option<Entity>) {
+  if let Some(entity) = entity {
+    commands.entity(entity).despawn();
+  }
+}
+
+// Color can't be scaled directly any more, this darkens the rgb and keeps the alpha
+fn darken(colour: Color, k: f32) -> Color {
+  let c = colour.to_srgba();
+  Color::srgba(c.red * k, c.green * k, c.blue * k, c.alpha)
+}
+
+fn glyphs(n: usize, rng: &mut impl Rng) -> String {
+  (0..n)
+    .map(|_| *(33..=126u8).collect::<Vec<_>>().choose(rng).unwrap() as char)
+    .collect::<String>()
+}
@@ -0,0 +1,62 @@
+use bevy::prelude::*;
+
+const MENU_FONT_SIZE: f32 = 20.0;
+
+// the F-key menus are each a dimmed full-screen overlay with one block of text in the middle,
+// `menu` tags the overlay and `text` the text
+pub fn spawn_menu(
+  commands: &mut Commands,
+  asset_server: &AssetServer,
+  menu: impl Bundle,
+  text: impl Bundle,
+) {
+  commands
+    .spawn((
+      Node {
+        position_type: PositionType::Absolute,
+        width: Val::Percent(100.0),
+        height: Val::Percent(100.0),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+      },
+      BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.7)),
+      GlobalZIndex(1),
+      Visibility::Hidden,
+      menu,
+    ))
+    .with_children(|parent| {
+      parent.spawn((
+        Text::default(),
+        TextFont {
+          font: asset_server.load("fonts/Monocraft.ttf"),
+          font_size: MENU_FONT_SIZE,
+          ..default()
+        },
+        TextColor(Color::WHITE),
+        text,
+      ));
+    });
+}
+
+// shows or hides the menu, its text is only built while it's open
+pub fn update_menu<M: Component, T: Component>(
+  open: bool,
+  menu_q: &mut Query<&mut Visibility, With<M>>,
+  text_q: &mut Query<&mut Text, With<T>>,
+  text: impl FnOnce() -> String,
+) {
+  for mut visibility in menu_q.iter_mut() {
+    *visibility = if open {
+      Visibility::Inherited
+    } else {
+      Visibility::Hidden
+    };
+  }
+  if open {
+    let text = text();
+    for mut menu_text in text_q.iter_mut() {
+      menu_text.0 = text.clone();
+    }
+  }
+}
@@ -0,0 +1,142 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::local::Local;
+use crate::locale::Locale;
+use crate::net::Clock;
+use crate::{GameEvent, Phase, Player, Texter};
+
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+pub enum Trigger {
+  Jump(usize),
+  Every(usize),
+  Event(GameEvent),
+}
+
+impl Trigger {
+  // fires when the count passes n rather than lands on it, co-op can jump two at once
+  pub fn fired(&self, last: usize, jumps: usize, events: &[GameEvent]) -> bool {
+    match *self {
+      Trigger::Jump(n) => last < n && jumps >= n,
+      Trigger::Every(n) => n > 0 && jumps > last && jumps / n > last / n,
+      Trigger::Event(event) => events.contains(&event),
+    }
+  }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum Animation {
+  #[default]
+  NONE,
+  SPIN,
+  POP,
+  SHAKE,
+}
+
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+pub struct Milestone {
+  pub trigger: Trigger,
+  pub message: String,
+  pub colour: (f32, f32, f32),
+  pub duration_ms: usize,
+  #[serde(default)]
+  pub sound: Option<String>,
+  #[serde(default)]
+  pub animation: Animation,
+}
+
+#[derive(Asset, TypePath, Deserialize)]
+pub struct MilestoneTable {
+  pub milestones: Vec<Milestone>,
+}
+
+#[derive(Resource, Default)]
+pub struct Milestones {
+  handle: Handle<MilestoneTable>,
+  last_jump_count: usize,
+}
+
+pub fn milestone_setup(mut milestones: ResMut<Milestones>, asset_server: Res<AssetServer>) {
+  milestones.handle = asset_server.load("data/default.milestones.ron");
+}
+
+pub fn milestone_update(
+  clock: Clock,
+  player: Res<Player>,
+  local: Res<Local>,
+  tables: Res<Assets<MilestoneTable>>,
+  asset_server: Res<AssetServer>,
+  locale: Res<Locale>,
+  mut milestones: ResMut<Milestones>,
+  mut texter: ResMut<Texter>,
+  phase: Res<State<Phase>>,
+  mut next_phase: ResMut<NextState<Phase>>,
+  mut commands: Commands,
+  mut events: MessageReader<GameEvent>,
+) {
+  let events = events.read().copied().collect::<Vec<_>>();
+  let jumps = local.team_jumps(&player);
+  let last = std::mem::replace(&mut milestones.last_jump_count, jumps);
+
+  let table = match tables.get(&milestones.handle) {
+    Some(table) => table,
+    None => return,
+  };
+
+  for milestone in &table.milestones {
+    if milestone.trigger.fired(last, jumps, &events) {
+      if let Some(sound) = &milestone.sound {
+        commands.spawn((
+          AudioPlayer::new(asset_server.load(sound.clone())),
+          PlaybackSettings::DESPAWN,
+        ));
+      }
+
+      // the score label only belongs to the milestone outside the corruption phases
+      if *phase.get() == Phase::DEFAULT {
+        next_phase.set(Phase::MILESTONE);
+      }
+      // translated once here, the score label and the hud both show it from texter
+      texter.milestone = Some(Milestone {
+        message: locale.tr(&milestone.message).to_string(),
+        ..milestone.clone()
+      });
+      texter.milestone_save = clock.now();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn jump_fires_on_passing_n() {
+    let trigger = Trigger::Jump(10);
+    assert!(trigger.fired(9, 10, &[]));
+    // co-op can jump twice in a tick
+    assert!(trigger.fired(9, 11, &[]));
+    assert!(!trigger.fired(10, 10, &[]));
+    assert!(!trigger.fired(10, 11, &[]));
+    // a reset isn't a pass
+    assert!(!trigger.fired(12, 0, &[]));
+  }
+
+  #[test]
+  fn every_fires_on_each_multiple() {
+    let trigger = Trigger::Every(100);
+    assert!(trigger.fired(99, 100, &[]));
+    assert!(trigger.fired(199, 201, &[]));
+    assert!(!trigger.fired(100, 100, &[]));
+    assert!(!trigger.fired(101, 199, &[]));
+    assert!(!trigger.fired(250, 0, &[]));
+    assert!(!Trigger::Every(0).fired(0, 5, &[]));
+  }
+
+  #[test]
+  fn event_fires_on_its_event() {
+    let trigger = Trigger::Event(GameEvent::JAVA);
+    assert!(trigger.fired(0, 0, &[GameEvent::JUMP, GameEvent::JAVA]));
+    assert!(!trigger.fired(0, 1, &[GameEvent::JUMP]));
+  }
+}
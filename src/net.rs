@@ -0,0 +1,487 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::sync::Mutex;
+
+use bevy::ecs::system::SystemParam;
+use bevy::input::keyboard::KeyboardInput;
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::camera::CameraRig;
+use crate::local::{fresh_player, set_mode, Controls, Local, Mode};
+use crate::locale::Locale;
+use crate::powerup::PowerUps;
+use crate::tween::Tweens;
+use crate::{reset_java, Java, Phase, Player, Settings, Texter, BACKGROUND_COLOR};
+
+// frames of input delay, what the relay round trip has to fit into before anyone stalls
+const INPUT_DELAY: usize = 4;
+const CODE_LEN: usize = 4;
+const DEFAULT_RELAY: &str = "ws://127.0.0.1:9001";
+
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub enum Status {
+  #[default]
+  OFFLINE,
+  CONNECTING,
+  HOSTING(String),
+  TYPING(String),
+  PLAYING,
+  ERROR(String),
+}
+
+// lines to and from the relay, the socket itself lives on its own thread. resources have to be
+// Sync and a Receiver isn't, the mutex is only ever taken through &mut so it never contends
+struct Link {
+  sender: Sender<String>,
+  receiver: Mutex<Receiver<String>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn connect(url: String) -> Link {
+  use std::io::ErrorKind;
+  use std::time::Duration;
+  use tungstenite::stream::MaybeTlsStream;
+  use tungstenite::Message;
+
+  let (sender, outbox) = channel::<String>();
+  let (inbox, receiver) = channel::<String>();
+
+  std::thread::spawn(move || {
+    let mut socket = match tungstenite::connect(url.as_str()) {
+      Ok((socket, _)) => socket,
+      Err(e) => {
+        // the details go to the log, the status line only gets what can be translated
+        warn!("can't reach {}: {}", url, e);
+        let _ = inbox.send("ERROR can't reach the relay".to_string());
+        return;
+      }
+    };
+    if let MaybeTlsStream::Plain(stream) = socket.get_mut() {
+      let _ = stream.set_nonblocking(true);
+    }
+
+    loop {
+      loop {
+        let line = match outbox.try_recv() {
+          Ok(line) => line,
+          Err(TryRecvError::Empty) => break,
+          Err(TryRecvError::Disconnected) => {
+            let _ = socket.close(None);
+            return;
+          }
+        };
+        match socket.write_message(Message::Text(line)) {
+          Err(tungstenite::Error::Io(e)) if e.kind() == ErrorKind::WouldBlock => {}
+          Err(_) => return,
+          Ok(_) => {}
+        }
+      }
+
+      match socket.read_message() {
+        Ok(Message::Text(line)) => {
+          if inbox.send(line).is_err() {
+            return;
+          }
+        }
+        Ok(Message::Close(_)) => return,
+        Ok(_) => {}
+        Err(tungstenite::Error::Io(e)) if e.kind() == ErrorKind::WouldBlock => {
+          std::thread::sleep(Duration::from_millis(1));
+        }
+        Err(_) => return,
+      }
+    }
+  });
+
+  Link {
+    sender,
+    receiver: Mutex::new(receiver),
+  }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn connect(_url: String) -> Link {
+  let (sender, _outbox) = channel::<String>();
+  let (inbox, receiver) = channel::<String>();
+  let _ = inbox.send("ERROR online play needs the native build".to_string());
+  Link {
+    sender,
+    receiver: Mutex::new(receiver),
+  }
+}
+
+// lockstep: every client runs the same frames from the same inputs, a frame only runs once
+// both slots' inputs for it have arrived, and local input is scheduled INPUT_DELAY frames ahead
+#[derive(Resource, Default)]
+pub struct Net {
+  pub status: Status,
+  link: Option<Link>,
+  pub slot: usize,
+  seed: u64,
+  // offline runs use fresh entropy unless something like the gym asks for a seed
+  seeded: bool,
+  frame: usize,
+  // the host's movement assists, both players move with these online
+  pub assists: u8,
+  // whether the last step ran its frame, the rest of the tick waits with it when it didn't
+  stalled: bool,
+  input_vec: [HashMap<usize, u8>; 2],
+}
+
+impl Net {
+  pub fn is_online(&self) -> bool {
+    self.status == Status::PLAYING
+  }
+
+  // whether this tick's frame can run, always true offline
+  fn ready(&self) -> bool {
+    !self.is_online() ||
+      self
+        .input_vec
+        .iter()
+        .all(|inputs| inputs.contains_key(&self.frame))
+  }
+
+  // whether this tick's frame ran, checked after player_move has stepped
+  pub fn ran(&self) -> bool {
+    !self.is_online() || !self.stalled
+  }
+
+  // online the simulation runs on frame time, so every client sees the same timers
+  pub fn clock(&self, tc: usize) -> usize {
+    if self.is_online() {
+      self.frame * 1000 / 60
+    } else {
+      tc
+    }
+  }
+
+  // tc is the clock() time, so every client draws the same numbers on the same frame
+  pub fn rng(&self, tc: usize) -> StdRng {
+    if self.is_online() || self.seeded {
+      StdRng::seed_from_u64(self.seed ^ (tc as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15))
+    } else {
+      StdRng::from_entropy()
+    }
+  }
+
+  pub fn seeded(seed: u64) -> Net {
+    Net {
+      seed,
+      seeded: true,
+      ..default()
+    }
+  }
+
+  // sends local input for a later frame and hands back this frame's inputs by slot, none while stalled
+  pub fn step(&mut self, controls: Controls) -> Option<[Controls; 2]> {
+    let ahead = self.frame + INPUT_DELAY;
+    if let Entry::Vacant(entry) = self.input_vec[self.slot].entry(ahead) {
+      entry.insert(controls.bits());
+      self.send(format!("INPUT {} {}", ahead, controls.bits()));
+    }
+
+    self.stalled = !self.ready();
+    if self.stalled {
+      return None;
+    }
+    let frame = self.frame;
+    let inputs =
+      [0, 1].map(|slot| Controls::from_bits(self.input_vec[slot].remove(&frame).unwrap()));
+    self.frame += 1;
+    Some(inputs)
+  }
+
+  // the other client's input for a frame, the first copy wins so a resend can't change a frame
+  fn receive(&mut self, slot: usize, frame: usize, bits: u8) {
+    if slot < 2 {
+      self.input_vec[slot].entry(frame).or_insert(bits);
+    }
+  }
+
+  // errors are the english text, relay ones included, and translate like any other
+  pub fn status_text(&self, locale: &Locale) -> String {
+    match &self.status {
+      Status::OFFLINE => String::new(),
+      Status::CONNECTING => locale.tr("connecting").to_string(),
+      Status::HOSTING(code) if code.is_empty() => locale.tr("hosting").to_string(),
+      Status::HOSTING(code) => locale.format("lobby {}, waiting", &[code]),
+      Status::TYPING(code) => locale.format("join code {}_", &[code]),
+      Status::PLAYING => locale.format("online as p{}", &[&(self.slot + 1)]),
+      Status::ERROR(message) => locale.format("net {}", &[&locale.tr(message)]),
+    }
+  }
+
+  fn send(&self, line: String) {
+    if let Some(link) = &self.link {
+      let _ = link.sender.send(line);
+    }
+  }
+
+  fn open(&mut self, first: String) {
+    let url = std::env::var("HASKELLHOP_RELAY").unwrap_or_else(|_| DEFAULT_RELAY.to_string());
+    self.link = Some(connect(url));
+    self.send(first);
+  }
+
+  // a client whose relay is the test itself, lines go in through the sender and come out of
+  // the receiver
+  #[cfg(test)]
+  pub fn loopback() -> (Net, Sender<String>, Receiver<String>) {
+    let (sender, outbox) = channel::<String>();
+    let (inbox, receiver) = channel::<String>();
+    let net = Net {
+      status: Status::CONNECTING,
+      link: Some(Link {
+        sender,
+        receiver: Mutex::new(receiver),
+      }),
+      ..default()
+    };
+    (net, inbox, outbox)
+  }
+
+  fn start(&mut self, slot: usize, seed: u64, assists: u8) {
+    self.slot = slot;
+    self.seed = seed;
+    self.assists = assists;
+    self.frame = 0;
+    self.status = Status::PLAYING;
+    // nobody has sent anything for the first frames yet, they run on empty input
+    for inputs in &mut self.input_vec {
+      inputs.clear();
+      inputs.extend((0..INPUT_DELAY).map(|frame| (frame, 0)));
+    }
+  }
+}
+
+// the simulation's clock for systems that don't otherwise need Net
+#[derive(SystemParam)]
+pub struct Clock<'w> {
+  time: Res<'w, Time>,
+  net: Res<'w, Net>,
+}
+
+impl Clock<'_> {
+  pub fn now(&self) -> usize {
+    self.net.clock(self.time.elapsed().as_millis() as usize)
+  }
+}
+
+// F2 hosts a lobby, F3 types a code to join one, Escape leaves
+pub fn net_lobby(
+  keyboard_input: Res<ButtonInput<KeyCode>>,
+  mut keys: MessageReader<KeyboardInput>,
+  asset_server: Res<AssetServer>,
+  mut net: ResMut<Net>,
+  mut local: ResMut<Local>,
+  mut player: ResMut<Player>,
+  mut java: ResMut<Java>,
+  mut texter: ResMut<Texter>,
+  mut tweens: ResMut<Tweens>,
+  mut powerups: ResMut<PowerUps>,
+  mut rig: ResMut<CameraRig>,
+  mut next_phase: ResMut<NextState<Phase>>,
+  mut background_colour: ResMut<ClearColor>,
+  settings: Res<Settings>,
+  mut commands: Commands,
+) {
+  let typed = keys
+    .read()
+    .filter(|key| key.state == ButtonState::Pressed)
+    .filter_map(|key| key.text.as_ref())
+    .flat_map(|text| text.chars())
+    .collect::<Vec<_>>();
+
+  if keyboard_input.just_pressed(KeyCode::Escape) && net.status != Status::OFFLINE {
+    let online = net.is_online();
+    *net = Net::default();
+    if online {
+      set_mode(&mut local, Mode::SOLO, &mut commands, &asset_server);
+    }
+    return;
+  }
+
+  match net.status.clone() {
+    Status::OFFLINE | Status::ERROR(_) => {
+      if keyboard_input.just_pressed(KeyCode::F2) {
+        let mode = if local.mode == Mode::VERSUS {
+          "versus"
+        } else {
+          "coop"
+        };
+        net.status = Status::HOSTING(String::new());
+        net.open(format!("HOST {} {}", mode, settings.assists()));
+      } else if keyboard_input.just_pressed(KeyCode::F3) {
+        net.status = Status::TYPING(String::new());
+      }
+    }
+    Status::TYPING(mut code) => {
+      code.extend(
+        typed
+          .iter()
+          .filter(|c| c.is_ascii_alphabetic())
+          .map(|c| c.to_ascii_uppercase()),
+      );
+      code.truncate(CODE_LEN);
+      if keyboard_input.just_pressed(KeyCode::Backspace) {
+        code.pop();
+      }
+
+      if keyboard_input.just_pressed(KeyCode::Enter) && code.len() == CODE_LEN {
+        net.status = Status::CONNECTING;
+        net.open(format!("JOIN {}", code));
+      } else {
+        net.status = Status::TYPING(code);
+      }
+    }
+    _ => {}
+  }
+
+  let lines = match &mut net.link {
+    Some(link) => {
+      let receiver = link.receiver.get_mut().unwrap();
+      let mut lines = receiver.try_iter().collect::<Vec<_>>();
+      if lines.is_empty() && matches!(receiver.try_recv(), Err(TryRecvError::Disconnected)) {
+        lines.push("ERROR relay closed".to_string());
+      }
+      lines
+    }
+    None => vec![],
+  };
+
+  for line in lines {
+    let mut words = line.split_whitespace();
+    match words.next() {
+      Some("CODE") => net.status = Status::HOSTING(words.next().unwrap_or("").to_string()),
+      Some("START") => {
+        let slot = words.next().and_then(|w| w.parse().ok()).unwrap_or(0);
+        let mode = match words.next() {
+          Some("versus") => Mode::VERSUS,
+          _ => Mode::COOP,
+        };
+        let seed = words.next().and_then(|w| w.parse().ok()).unwrap_or(0);
+        let assists = words
+          .next()
+          .and_then(|w| w.parse().ok())
+          .unwrap_or(Settings::default().assists());
+        net.start(slot, seed, assists);
+        // the clock starts over at frame 0 and both machines have to start from the same
+        // state, so nothing the offline run left behind can carry over
+        *texter = Texter {
+          entity: texter.entity,
+          ..default()
+        };
+        reset_java(&mut java, &mut commands);
+        // java enters at the camera, which follows the players from where it is
+        *rig = CameraRig::default();
+        next_phase.set(Phase::DEFAULT);
+        *background_colour = ClearColor(BACKGROUND_COLOR);
+        tweens.clear();
+        // pickups stop online, a buff left running would only slow this client
+        powerups.clear(&mut commands);
+
+        // slot 0 starts on the left on every machine
+        set_mode(&mut local, mode, &mut commands, &asset_server);
+        *player = Player {
+          entity: player.entity,
+          best_jump_count: player.best_jump_count,
+          ..fresh_player(slot == 1)
+        };
+        local.player = Player {
+          entity: local.player.entity,
+          ..fresh_player(slot == 0)
+        };
+      }
+      Some("INPUT") => {
+        let mut numbers = words.filter_map(|w| w.parse::<usize>().ok());
+        if let (Some(slot), Some(frame), Some(bits)) =
+          (numbers.next(), numbers.next(), numbers.next())
+        {
+          net.receive(slot, frame, bits as u8);
+        }
+      }
+      Some("LEFT") => {
+        net.link = None;
+        net.status = Status::ERROR("other player left".to_string());
+        set_mode(&mut local, Mode::SOLO, &mut commands, &asset_server);
+      }
+      Some("ERROR") => {
+        let online = net.is_online();
+        net.link = None;
+        net.status = Status::ERROR(words.collect::<Vec<_>>().join(" "));
+        if online {
+          set_mode(&mut local, Mode::SOLO, &mut commands, &asset_server);
+        }
+      }
+      _ => {}
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn started(slot: usize) -> Net {
+    let mut net = Net::default();
+    net.start(slot, 7, 0);
+    net
+  }
+
+  fn jump() -> Controls {
+    Controls {
+      jump: true,
+      ..default()
+    }
+  }
+
+  #[test]
+  fn first_frames_run_on_empty_input() {
+    let mut net = started(0);
+    for frame in 0..INPUT_DELAY {
+      let inputs = net.step(jump()).expect("pre-filled frame stalled");
+      assert_eq!(inputs.map(Controls::bits), [0, 0], "frame {}", frame);
+    }
+    // nothing from the other slot yet
+    assert!(net.step(jump()).is_none());
+    assert!(!net.ran());
+  }
+
+  #[test]
+  fn stalls_until_both_slots_have_input() {
+    let mut net = started(1);
+    for _ in 0..INPUT_DELAY {
+      net.step(Controls::default()).unwrap();
+    }
+    assert!(net.step(jump()).is_none());
+    // a stalled step doesn't move the frame on
+    assert!(net.step(Controls::default()).is_none());
+    net.receive(0, INPUT_DELAY, jump().bits());
+    let inputs = net.step(Controls::default()).unwrap();
+    // this slot's input for the frame went out INPUT_DELAY frames ago
+    assert_eq!(inputs.map(Controls::bits), [jump().bits(), 0]);
+    assert!(net.ran());
+  }
+
+  #[test]
+  fn duplicate_input_is_ignored() {
+    let mut net = started(0);
+    net.receive(1, INPUT_DELAY, jump().bits());
+    net.receive(1, INPUT_DELAY, 0);
+    // and an already pre-filled frame keeps its empty input
+    net.receive(1, 0, jump().bits());
+    assert_eq!(net.step(Controls::default()).unwrap()[1].bits(), 0);
+    for _ in 1..INPUT_DELAY {
+      net.step(Controls::default()).unwrap();
+    }
+    assert_eq!(
+      net.step(Controls::default()).unwrap()[1].bits(),
+      jump().bits()
+    );
+  }
+}
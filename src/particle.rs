@@ -0,0 +1,181 @@
+use bevy::color::palettes::css;
+use bevy::prelude::*;
+use rand::{thread_rng, Rng};
+
+use crate::access::Access;
+use crate::camera::CameraRig;
+use crate::{despawn, glyphs, Phase, GRAVITY, WINDOW_HEIGHT, WINDOW_WIDTH};
+
+const MAX_PARTICLES: usize = 300;
+const GLYPH_EVERY_MS: usize = 50;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BurstKind {
+  DUST,
+  SPARK,
+  TRAIL(Color),
+}
+
+#[derive(Message)]
+pub struct Burst {
+  pub kind: BurstKind,
+  pub i: f32,
+  pub j: f32,
+}
+
+#[derive(PartialEq, Clone)]
+struct Particle {
+  entity: Option<Entity>,
+  colour: Color,
+  i: f32,
+  j: f32,
+  vel_i: f32,
+  vel_j: f32,
+  gravity: f32,
+  time_save: usize,
+  life_ms: usize,
+}
+
+#[derive(Resource, Default)]
+pub struct Particles {
+  time_save: usize,
+  particle_vec: Vec<Particle>,
+}
+
+pub fn particle_emit(
+  time: Res<Time>,
+  phase: Res<State<Phase>>,
+  rig: Res<CameraRig>,
+  asset_server: Res<AssetServer>,
+  access: Res<Access>,
+  mut particles: ResMut<Particles>,
+  mut bursts: MessageReader<Burst>,
+  mut commands: Commands,
+) {
+  let mut rng = thread_rng();
+  let tc = time.elapsed().as_millis() as usize;
+
+  for burst in bursts.read() {
+    let (count, colour, speed, gravity, life_ms) = match burst.kind {
+      BurstKind::DUST => (12, Color::srgba(0.9, 0.8, 0.7, 0.8), 2.0, -0.05, 400),
+      BurstKind::SPARK => (16, css::YELLOW.into(), 6.0, GRAVITY / 40.0, 300),
+      BurstKind::TRAIL(colour) => (2, colour, 0.5, 0.0, 350),
+    };
+
+    for _ in 0..count {
+      if particles.particle_vec.len() >= MAX_PARTICLES {
+        break;
+      }
+
+      let angle = match burst.kind {
+        BurstKind::DUST => rng.gen_range(0.0..std::f32::consts::PI),
+        BurstKind::SPARK | BurstKind::TRAIL(_) => rng.gen_range(0.0..std::f32::consts::TAU),
+      };
+      let speed = speed * rng.gen_range(0.3..1.0);
+      let size = rng.gen_range(3.0..7.0);
+
+      particles.particle_vec.push(Particle {
+        colour,
+        i: burst.i,
+        j: burst.j,
+        vel_i: angle.sin() * speed,
+        vel_j: angle.cos() * speed,
+        gravity,
+        time_save: tc,
+        life_ms,
+        entity: Some(
+          commands
+            .spawn((
+              Sprite::from_color(colour, Vec2::new(size, size)),
+              Transform::from_xyz(burst.j, burst.i, 1.0),
+            ))
+            .id(),
+        ),
+      });
+    }
+  }
+
+  // the glyph rain flickers as it falls, it stays off with reduced flashing
+  if *phase.get() == Phase::CORRUPT_B &&
+    !access.reduce_flashing &&
+    tc - particles.time_save >= GLYPH_EVERY_MS &&
+    particles.particle_vec.len() < MAX_PARTICLES
+  {
+    particles.time_save = tc;
+    let i = rig.i + rng.gen_range(-(WINDOW_HEIGHT / 2.0)..(WINDOW_HEIGHT / 2.0));
+    let j = rig.j + rng.gen_range(-(WINDOW_WIDTH / 2.0)..(WINDOW_WIDTH / 2.0));
+
+    let colour = access.palette.apply(css::LIME.into());
+    particles.particle_vec.push(Particle {
+      colour,
+      i,
+      j,
+      vel_i: -rng.gen_range(0.5..2.0),
+      vel_j: 0.0,
+      gravity: 0.0,
+      time_save: tc,
+      life_ms: 1500,
+      entity: Some(
+        commands
+          .spawn((
+            Text2d::new(glyphs(1, &mut rng)),
+            TextFont {
+              font: asset_server.load("fonts/Monocraft.ttf"),
+              font_size: rng.gen_range(12.0..30.0),
+              ..default()
+            },
+            TextColor(colour),
+            Transform::from_xyz(j, i, 1.0),
+          ))
+          .id(),
+      ),
+    });
+  }
+}
+
+pub fn particle_update(
+  time: Res<Time>,
+  mut particles: ResMut<Particles>,
+  mut transform_q: Query<&mut Transform>,
+  mut sprite_q: Query<&mut Sprite>,
+  mut text_q: Query<(&mut Text2d, &mut TextColor)>,
+  mut commands: Commands,
+) {
+  let mut rng = thread_rng();
+  let tc = time.elapsed().as_millis() as usize;
+
+  particles.particle_vec.retain_mut(|particle| {
+    let entity = match particle.entity {
+      Some(entity) => entity,
+      None => return false,
+    };
+    // emitted on frame time, which can be a little ahead of the tick's
+    let age = tc.saturating_sub(particle.time_save);
+    if age >= particle.life_ms {
+      despawn(&mut commands, Some(entity));
+      return false;
+    }
+
+    particle.i += particle.vel_i;
+    particle.j += particle.vel_j;
+    particle.vel_i -= particle.gravity;
+
+    let alpha = particle.colour.alpha() * (1.0 - age as f32 / particle.life_ms as f32);
+    let colour = particle.colour.with_alpha(alpha);
+
+    if let Ok(mut transform) = transform_q.get_mut(entity) {
+      transform.translation.x = particle.j;
+      transform.translation.y = particle.i;
+    }
+    if let Ok(mut sprite) = sprite_q.get_mut(entity) {
+      sprite.color = colour;
+    }
+    if let Ok((mut text, mut text_colour)) = text_q.get_mut(entity) {
+      text_colour.0 = colour;
+      if rng.gen_bool(0.1) {
+        text.0 = glyphs(1, &mut rng);
+      }
+    }
+    true
+  });
+}
@@ -0,0 +1,224 @@
+use bevy::camera::visibility::RenderLayers;
+use bevy::camera::RenderTarget;
+use bevy::prelude::*;
+use bevy::render::render_resource::{
+  AsBindGroup, CachedPipelineState, Extent3d, PipelineCache, PipelineDescriptor, TextureFormat,
+};
+use bevy::render::MainWorld;
+use bevy::shader::{Shader, ShaderRef};
+use bevy::sprite_render::{Material2d, MeshMaterial2d};
+use bevy::window::{PrimaryWindow, WindowRef, WindowResized};
+use rand::{thread_rng, Rng};
+
+use crate::access::Access;
+use crate::camera::MainCamera;
+use crate::{Phase, Settings};
+
+// everything else draws on layer 0
+const POSTFX_LAYER: usize = 31;
+const INTENSITY_LERP: f32 = 0.05;
+const SHADER_PATH: &str = "shaders/glitch.wgsl";
+
+#[derive(Asset, TypePath, AsBindGroup, Clone)]
+pub struct GlitchMaterial {
+  #[texture(0)]
+  #[sampler(1)]
+  source: Handle<Image>,
+  #[uniform(2)]
+  params: Vec4,
+}
+
+impl Material2d for GlitchMaterial {
+  fn fragment_shader() -> ShaderRef {
+    SHADER_PATH.into()
+  }
+}
+
+#[derive(Component)]
+pub struct PostFxCamera;
+
+#[derive(Resource, Default)]
+pub struct PostFx {
+  image: Handle<Image>,
+  quad: Handle<Mesh>,
+  material: Handle<GlitchMaterial>,
+  // the same handle the material's pipeline compiles, to find that pipeline in the cache
+  shader: Handle<Shader>,
+  intensity: f32,
+  // the glitch pipeline didn't build, the game draws straight to the window whatever F9 says
+  failed: bool,
+}
+
+fn target_size(window: &Window) -> Extent3d {
+  Extent3d {
+    width: window.physical_width().max(1),
+    height: window.physical_height().max(1),
+    ..default()
+  }
+}
+
+// the game camera renders into an image, a second camera draws that image on a quad through the glitch material
+pub fn postfx_setup(
+  window_q: Query<&Window, With<PrimaryWindow>>,
+  mut settings: ResMut<Settings>,
+  mut postfx: ResMut<PostFx>,
+  mut images: ResMut<Assets<Image>>,
+  mut meshes: ResMut<Assets<Mesh>>,
+  mut materials: ResMut<Assets<GlitchMaterial>>,
+  asset_server: Res<AssetServer>,
+  mut commands: Commands,
+) {
+  postfx.shader = asset_server.load(SHADER_PATH);
+  let window = match window_q.single() {
+    Ok(window) => window,
+    Err(_) => {
+      warn!("no primary window, post-processing is off");
+      settings.post_process = false;
+      return;
+    }
+  };
+  let size = target_size(window);
+
+  postfx.image = images.add(Image::new_target_texture(
+    size.width,
+    size.height,
+    TextureFormat::Bgra8UnormSrgb,
+    None,
+  ));
+  postfx.quad = meshes.add(Rectangle::new(window.width(), window.height()));
+  postfx.material = materials.add(GlitchMaterial {
+    source: postfx.image.clone(),
+    params: Vec4::ZERO,
+  });
+
+  commands.spawn((
+    Mesh2d(postfx.quad.clone()),
+    MeshMaterial2d(postfx.material.clone()),
+    RenderLayers::layer(POSTFX_LAYER),
+  ));
+
+  commands.spawn((
+    Camera2d,
+    Camera {
+      order: 1,
+      ..default()
+    },
+    RenderLayers::layer(POSTFX_LAYER),
+    PostFxCamera,
+  ));
+}
+
+type MainOnly = (With<MainCamera>, Without<PostFxCamera>);
+type PostOnly = (With<PostFxCamera>, Without<MainCamera>);
+
+pub fn postfx_toggle(
+  settings: Res<Settings>,
+  postfx: Res<PostFx>,
+  mut main_q: Query<(Entity, &mut RenderTarget), MainOnly>,
+  mut post_q: Query<(Entity, &mut Camera), PostOnly>,
+  mut shown: Local<Option<bool>>,
+  mut commands: Commands,
+) {
+  let on = settings.post_process && !postfx.failed;
+  if *shown == Some(on) {
+    return;
+  }
+  *shown = Some(on);
+
+  // the hud goes on whichever camera draws to the window, so it stays crisp on top of the effect
+  for (entity, mut target) in &mut main_q {
+    *target = if on {
+      commands.entity(entity).remove::<IsDefaultUiCamera>();
+      RenderTarget::Image(postfx.image.clone().into())
+    } else {
+      commands.entity(entity).insert(IsDefaultUiCamera);
+      RenderTarget::Window(WindowRef::Primary)
+    };
+  }
+
+  for (entity, mut camera) in &mut post_q {
+    camera.is_active = on;
+    if on {
+      commands.entity(entity).insert(IsDefaultUiCamera);
+    } else {
+      commands.entity(entity).remove::<IsDefaultUiCamera>();
+    }
+  }
+}
+
+// runs in the render world's extract, where the pipeline cache lives. a shader the backend can't
+// compile (webgl2 with some drivers) leaves the pipeline in Err, and the pass is skipped from then on
+pub fn postfx_watch(pipeline_cache: Res<PipelineCache>, mut main_world: ResMut<MainWorld>) {
+  let mut postfx = main_world.resource_mut::<PostFx>();
+  if postfx.failed {
+    return;
+  }
+  let shader = postfx.shader.id();
+  let failed =
+    pipeline_cache
+      .pipelines()
+      .find_map(|pipeline| match (&pipeline.descriptor, &pipeline.state) {
+        (PipelineDescriptor::RenderPipelineDescriptor(descriptor), CachedPipelineState::Err(e))
+          if descriptor
+            .fragment
+            .as_ref()
+            .is_some_and(|fragment| fragment.shader.id() == shader) =>
+        {
+          Some(e)
+        }
+        _ => None,
+      });
+  if let Some(e) = failed {
+    warn!("glitch pipeline failed, post-processing is off: {}", e);
+    postfx.failed = true;
+  }
+}
+
+pub fn postfx_resize(
+  window_q: Query<&Window, With<PrimaryWindow>>,
+  postfx: Res<PostFx>,
+  mut resized: MessageReader<WindowResized>,
+  mut images: ResMut<Assets<Image>>,
+  mut meshes: ResMut<Assets<Mesh>>,
+) {
+  for event in resized.read() {
+    if let Ok(window) = window_q.get(event.window) {
+      if let Some(image) = images.get_mut(&postfx.image) {
+        image.resize(target_size(window));
+      }
+      if let Some(quad) = meshes.get_mut(&postfx.quad) {
+        *quad = Rectangle::new(window.width(), window.height()).into();
+      }
+    }
+  }
+}
+
+pub fn postfx_update(
+  time: Res<Time>,
+  phase: Res<State<Phase>>,
+  access: Res<Access>,
+  mut postfx: ResMut<PostFx>,
+  mut materials: ResMut<Assets<GlitchMaterial>>,
+) {
+  let mut rng = thread_rng();
+  // the glitch flickers at any strength, so reduced flashing turns it off rather than down
+  let target = match phase.get() {
+    _ if access.reduce_flashing => 0.0,
+    Phase::DEFAULT | Phase::MILESTONE => 0.0,
+    Phase::CORRUPT_A => 0.3,
+    Phase::CORRUPT_B => 0.8,
+    Phase::JAVA => 0.4,
+  };
+  postfx.intensity += (target - postfx.intensity) * INTENSITY_LERP;
+
+  let invert = if *phase.get() == Phase::CORRUPT_B && !access.reduce_flashing && rng.gen_bool(0.03)
+  {
+    1.0
+  } else {
+    0.0
+  };
+
+  if let Some(material) = materials.get_mut(&postfx.material) {
+    material.params = Vec4::new(postfx.intensity, time.elapsed_secs(), invert, 0.0);
+  }
+}
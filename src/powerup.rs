@@ -0,0 +1,236 @@
+use bevy::color::palettes::css;
+use bevy::math::bounding::{Aabb2d, IntersectsVolume};
+use bevy::prelude::*;
+use rand::{prelude::SliceRandom, Rng};
+
+use crate::access::Access;
+use crate::camera::CameraRig;
+use crate::local::Local;
+use crate::locale::Locale;
+use crate::net::Net;
+use crate::{despawn, lookup, GameEvent, Player, GRAVITY, WINDOW_HEIGHT, WINDOW_WIDTH};
+
+const POWERUP_SPAWN_MS: usize = 8000;
+const POWERUP_LIFETIME_MS: usize = 6000;
+const POWERUP_DURATION_MS: usize = 8000;
+const POWERUP_SIZE: f32 = 30.0;
+const MAGNET_RANGE: f32 = 300.0;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Kind {
+  DOUBLE_JUMP,
+  SHIELD,
+  SLOW_TIME,
+  MAGNET,
+  BIG_DEFLECT,
+}
+
+impl Kind {
+  const ALL: [Kind; 5] = [
+    Kind::DOUBLE_JUMP,
+    Kind::SHIELD,
+    Kind::SLOW_TIME,
+    Kind::MAGNET,
+    Kind::BIG_DEFLECT,
+  ];
+
+  fn colour(self) -> Color {
+    match self {
+      Kind::DOUBLE_JUMP => css::YELLOW,
+      Kind::SHIELD => css::AQUA,
+      Kind::SLOW_TIME => css::PURPLE,
+      Kind::MAGNET => css::ORANGE,
+      Kind::BIG_DEFLECT => css::LIMEGREEN,
+    }
+    .into()
+  }
+
+  fn name(self) -> &'static str {
+    match self {
+      Kind::DOUBLE_JUMP => "double jump",
+      Kind::SHIELD => "shield",
+      Kind::SLOW_TIME => "slow time",
+      Kind::MAGNET => "magnet",
+      Kind::BIG_DEFLECT => "big deflect",
+    }
+  }
+}
+
+#[derive(PartialEq, Clone)]
+struct Pickup {
+  entity: Option<Entity>,
+  kind: Kind,
+  i: f32,
+  j: f32,
+  vel_i: f32,
+  vel_j: f32,
+  size: Vec2,
+  time_save: usize,
+}
+
+#[derive(Resource, Default)]
+pub struct PowerUps {
+  time_save: usize,
+  pickups: Vec<Pickup>,
+  active: Vec<(Kind, usize)>,
+}
+
+impl PowerUps {
+  pub fn is_active(&self, kind: Kind) -> bool {
+    self.active.iter().any(|(k, _)| *k == kind)
+  }
+
+  // a new run starts without pickups or buffs, the spawn timer carries on
+  pub fn clear(&mut self, commands: &mut Commands) {
+    for pickup in self.pickups.drain(..) {
+      despawn(commands, pickup.entity);
+    }
+    self.active.clear();
+  }
+
+  pub fn time_scale(&self) -> f32 {
+    if self.is_active(Kind::SLOW_TIME) {
+      0.5
+    } else {
+      1.0
+    }
+  }
+
+  pub fn timers(&self, tc: usize, locale: &Locale) -> String {
+    self
+      .active
+      .iter()
+      .map(|(kind, until)| {
+        format!(
+          "{} {:.1}",
+          locale.tr(kind.name()),
+          until.saturating_sub(tc) as f32 / 1000.0
+        )
+      })
+      .collect::<Vec<_>>()
+      .join("\n")
+  }
+}
+
+pub fn powerup_update(
+  time: Res<Time>,
+  mut powerups: ResMut<PowerUps>,
+  mut player: ResMut<Player>,
+  mut local: ResMut<Local>,
+  net: Res<Net>,
+  rig: Res<CameraRig>,
+  access: Res<Access>,
+  mut transform_q: Query<&mut Transform>,
+  mut commands: Commands,
+  mut events: MessageWriter<GameEvent>,
+) {
+  // pickups aren't part of the lockstep simulation yet, so online runs go without them
+  if net.is_online() {
+    return;
+  }
+
+  let tc = time.elapsed().as_millis() as usize;
+  let mut rng = net.rng(tc);
+  let floor = -(WINDOW_HEIGHT / 2.0) + (WINDOW_HEIGHT * 0.1);
+
+  let mut spawned = None;
+  if tc - powerups.time_save >= POWERUP_SPAWN_MS {
+    powerups.time_save = tc;
+    let kind = *Kind::ALL.choose(&mut rng).unwrap();
+    let i = WINDOW_HEIGHT / 2.0;
+    let j = rig.j + rng.gen_range(-(WINDOW_WIDTH / 2.0) * 0.8..(WINDOW_WIDTH / 2.0) * 0.8);
+    let size = Vec2::new(POWERUP_SIZE, POWERUP_SIZE);
+
+    spawned = Some(Pickup {
+      kind,
+      i,
+      j,
+      vel_i: 0.0,
+      vel_j: 0.0,
+      size,
+      time_save: tc,
+      entity: Some(
+        commands
+          .spawn((
+            Sprite::from_color(access.palette.apply(kind.colour()), size),
+            Transform::from_xyz(j, i, 0.0),
+          ))
+          .id(),
+      ),
+    });
+  }
+
+  // pickups and their buffs are shared between local players
+  let mut players = vec![&mut *player];
+  if local.is_active() {
+    players.push(&mut local.player);
+  }
+  let magnet = powerups.is_active(Kind::MAGNET);
+  let mut collected = vec![];
+
+  powerups.pickups.retain_mut(|pickup| {
+    if tc - pickup.time_save >= POWERUP_LIFETIME_MS {
+      despawn(&mut commands, pickup.entity);
+      return false;
+    }
+
+    let nearest = players
+      .iter()
+      .map(|player| Vec2::new(player.j - pickup.j, player.i - pickup.i))
+      .min_by(|a, b| a.length().total_cmp(&b.length()))
+      .unwrap();
+
+    if magnet && nearest.length() <= MAGNET_RANGE {
+      pickup.vel_j = nearest.x / 10.0;
+      pickup.vel_i = nearest.y / 10.0;
+    } else if pickup.i > floor {
+      pickup.vel_j = 0.0;
+      pickup.vel_i -= GRAVITY / 40.0;
+    } else {
+      pickup.vel_j = 0.0;
+      pickup.vel_i = 0.0;
+    }
+
+    pickup.i = (pickup.i + pickup.vel_i).max(floor);
+    pickup.j += pickup.vel_j;
+
+    let pickup_tl = Vec3::new(pickup.j, pickup.i, 0.0);
+    let pickup_box = Aabb2d::new(pickup_tl.xy(), pickup.size / 2.0);
+    if players.iter().any(|player| {
+      Aabb2d::new(Vec2::new(player.j, player.i), player.size / 2.0).intersects(&pickup_box)
+    }) {
+      collected.push(pickup.kind);
+      despawn(&mut commands, pickup.entity);
+      return false;
+    }
+
+    match lookup(&mut transform_q, pickup.entity, "pickup") {
+      Some(mut transform) => transform.translation = pickup_tl,
+      None => return false,
+    }
+    true
+  });
+  // like java's apples, its entity only exists once the commands are applied
+  powerups.pickups.extend(spawned);
+
+  for kind in collected {
+    events.write(GameEvent::POWERUP);
+    powerups.active.retain(|(k, _)| *k != kind);
+    powerups.active.push((kind, tc + POWERUP_DURATION_MS));
+  }
+  powerups.active.retain(|(_, until)| *until > tc);
+
+  for player in players {
+    player.max_air_jumps = if powerups.is_active(Kind::DOUBLE_JUMP) {
+      1
+    } else {
+      0
+    };
+    player.shield = powerups.is_active(Kind::SHIELD);
+    player.deflect_scale = if powerups.is_active(Kind::BIG_DEFLECT) {
+      2.0
+    } else {
+      1.0
+    };
+  }
+}
@@ -0,0 +1,43 @@
+use std::marker::PhantomData;
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+
+// the data files under assets/ are each one ron value, told apart by their extension
+#[derive(TypePath)]
+pub struct RonLoader<T> {
+  extensions: [&'static str; 1],
+  asset: PhantomData<fn() -> T>,
+}
+
+impl<T> RonLoader<T> {
+  pub fn new(extension: &'static str) -> RonLoader<T> {
+    RonLoader {
+      extensions: [extension],
+      asset: PhantomData,
+    }
+  }
+}
+
+impl<T: Asset + DeserializeOwned> AssetLoader for RonLoader<T> {
+  type Asset = T;
+  type Settings = ();
+  type Error = anyhow::Error;
+
+  async fn load(
+    &self,
+    reader: &mut dyn Reader,
+    _settings: &(),
+    _load_context: &mut LoadContext<'_>,
+  ) -> anyhow::Result<T> {
+    let mut bytes = vec![];
+    reader.read_to_end(&mut bytes).await?;
+    Ok(ron::de::from_bytes::<T>(&bytes)?)
+  }
+
+  fn extensions(&self) -> &[&str] {
+    &self.extensions
+  }
+}
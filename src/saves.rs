@@ -0,0 +1,44 @@
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+// everything the game writes goes under saves/, the web build has no filesystem and keeps nothing
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load<T: DeserializeOwned>(path: &str) -> Option<T> {
+  let text = std::fs::read_to_string(path).ok()?;
+  match ron::from_str(&text) {
+    Ok(value) => Some(value),
+    Err(e) => {
+      warn!("ignoring {}: {}", path, e);
+      None
+    }
+  }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_text(path: &str, text: &str) -> anyhow::Result<()> {
+  if let Some(dir) = std::path::Path::new(path).parent() {
+    std::fs::create_dir_all(dir)?;
+  }
+  std::fs::write(path, text)?;
+  Ok(())
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load<T: DeserializeOwned>(_path: &str) -> Option<T> {
+  None
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save_text(_path: &str, _text: &str) -> anyhow::Result<()> {
+  Ok(())
+}
+
+pub fn save<T: Serialize>(path: &str, value: &T) {
+  let result = ron::ser::to_string(value)
+    .map_err(anyhow::Error::from)
+    .and_then(|text| save_text(path, &text));
+  if let Err(e) = result {
+    warn!("couldn't save {}: {}", path, e);
+  }
+}
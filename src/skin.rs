@@ -0,0 +1,248 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::achievement::{Achievement, Achievements};
+use crate::bot::Bot;
+use crate::locale::Locale;
+use crate::menu::{spawn_menu, update_menu};
+use crate::particle::{Burst, BurstKind};
+use crate::saves;
+use crate::Player;
+
+const RECORD_PATH: &str = "saves/skins.ron";
+// every texture is drawn at the size haskell.png has, so a skin never changes the player's size
+const SKIN_SIZE: f32 = 512.0;
+const TRAIL_EVERY_MS: usize = 30;
+const HAT_FONT_SIZE: f32 = 30.0;
+const PICK_KEYS: [KeyCode; 9] = [
+  KeyCode::Digit1,
+  KeyCode::Digit2,
+  KeyCode::Digit3,
+  KeyCode::Digit4,
+  KeyCode::Digit5,
+  KeyCode::Digit6,
+  KeyCode::Digit7,
+  KeyCode::Digit8,
+  KeyCode::Digit9,
+];
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum Unlock {
+  Always,
+  // best jump count across every run so far
+  Best(usize),
+  Achievement(Achievement),
+}
+
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+pub struct Skin {
+  pub name: String,
+  pub texture: String,
+  #[serde(default = "white")]
+  pub tint: (f32, f32, f32),
+  #[serde(default)]
+  pub trail: Option<(f32, f32, f32)>,
+  // drawn as text above the player
+  #[serde(default)]
+  pub hat: Option<String>,
+  #[serde(default = "always")]
+  pub unlock: Unlock,
+}
+
+fn white() -> (f32, f32, f32) {
+  (1.0, 1.0, 1.0)
+}
+
+fn always() -> Unlock {
+  Unlock::Always
+}
+
+#[derive(Asset, TypePath, Deserialize)]
+pub struct SkinTable {
+  pub skins: Vec<Skin>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Record {
+  selected: String,
+  best: usize,
+}
+
+#[derive(Resource, Default)]
+pub struct Skins {
+  handle: Handle<SkinTable>,
+  record: Record,
+  // player one's tint, read by draw_player so the hurt blink and shield still show through
+  pub tint: Srgba,
+  hat_entity: Option<Entity>,
+  trail_save: usize,
+  menu_open: bool,
+}
+
+impl Skins {
+  fn is_unlocked(&self, skin: &Skin, achievements: &Achievements) -> bool {
+    match skin.unlock {
+      Unlock::Always => true,
+      Unlock::Best(n) => self.record.best >= n,
+      Unlock::Achievement(achievement) => achievements.is_unlocked(achievement),
+    }
+  }
+
+  // falls back to the first skin when the saved one is gone or locked
+  fn selected<'a>(&self, table: &'a SkinTable, achievements: &Achievements) -> Option<&'a Skin> {
+    table
+      .skins
+      .iter()
+      .find(|skin| skin.name == self.record.selected && self.is_unlocked(skin, achievements))
+      .or_else(|| table.skins.first())
+  }
+
+  fn menu_text(&self, table: &SkinTable, achievements: &Achievements, locale: &Locale) -> String {
+    let selected = self
+      .selected(table, achievements)
+      .map(|skin| skin.name.as_str());
+    let mut lines = vec![locale.tr("skins").to_string()];
+    for (k, skin) in table.skins.iter().take(PICK_KEYS.len()).enumerate() {
+      let mark = if Some(skin.name.as_str()) == selected {
+        ">"
+      } else {
+        " "
+      };
+      let status = match skin.unlock {
+        _ if self.is_unlocked(skin, achievements) => String::new(),
+        Unlock::Best(n) => format!(" - {}", locale.format("reach {} jumps", &[&n])),
+        Unlock::Achievement(achievement) => format!(
+          " - {}",
+          locale.format("unlock \"{}\"", &[&locale.tr(achievement.name())])
+        ),
+        Unlock::Always => String::new(),
+      };
+      lines.push(format!("{} {} {}{}", mark, k + 1, skin.name, status));
+    }
+    lines.push(String::new());
+    lines.push(locale.tr("1-9 to pick, F7 to close").to_string());
+    lines.join("\n")
+  }
+}
+
+#[derive(Component)]
+pub struct SkinMenu;
+
+#[derive(Component)]
+pub struct SkinMenuText;
+
+pub fn skin_setup(
+  mut skins: ResMut<Skins>,
+  mut commands: Commands,
+  asset_server: Res<AssetServer>,
+) {
+  skins.handle = asset_server.load("data/default.skins.ron");
+  skins.record = saves::load(RECORD_PATH).unwrap_or_default();
+
+  skins.hat_entity = Some(
+    commands
+      .spawn((
+        Text2d::default(),
+        TextFont {
+          font: asset_server.load("fonts/Monocraft.ttf"),
+          font_size: HAT_FONT_SIZE,
+          ..default()
+        },
+        TextColor(Color::WHITE),
+        Transform::default(),
+      ))
+      .id(),
+  );
+
+  spawn_menu(&mut commands, &asset_server, SkinMenu, SkinMenuText);
+}
+
+// cosmetic only, player one wears the selected skin, everyone else keeps theirs
+pub fn skin_update(
+  time: Res<Time>,
+  keyboard_input: Res<ButtonInput<KeyCode>>,
+  player: Res<Player>,
+  bot: Res<Bot>,
+  achievements: Res<Achievements>,
+  tables: Res<Assets<SkinTable>>,
+  asset_server: Res<AssetServer>,
+  locale: Res<Locale>,
+  mut skins: ResMut<Skins>,
+  mut sprite_q: Query<&mut Sprite>,
+  mut hat_q: Query<(&mut Text2d, &mut Transform)>,
+  mut menu_q: Query<&mut Visibility, With<SkinMenu>>,
+  mut menu_text_q: Query<&mut Text, With<SkinMenuText>>,
+  mut bursts: MessageWriter<Burst>,
+) {
+  let tc = time.elapsed().as_millis() as usize;
+
+  if !bot.active && player.jump_count > skins.record.best {
+    skins.record.best = player.jump_count;
+    saves::save(RECORD_PATH, &skins.record);
+  }
+
+  let table = match tables.get(&skins.handle) {
+    Some(table) => table,
+    None => return,
+  };
+
+  if keyboard_input.just_pressed(KeyCode::F7) {
+    skins.menu_open = !skins.menu_open;
+  }
+  if skins.menu_open {
+    for (k, key) in PICK_KEYS.iter().enumerate() {
+      let skin = match table.skins.get(k) {
+        Some(skin) => skin,
+        None => break,
+      };
+      if keyboard_input.just_pressed(*key) && skins.is_unlocked(skin, &achievements) {
+        skins.record.selected = skin.name.clone();
+        saves::save(RECORD_PATH, &skins.record);
+      }
+    }
+  }
+  update_menu(skins.menu_open, &mut menu_q, &mut menu_text_q, || {
+    skins.menu_text(table, &achievements, &locale)
+  });
+
+  let skin = match skins.selected(table, &achievements) {
+    Some(skin) => skin.clone(),
+    None => return,
+  };
+  let (r, g, b) = skin.tint;
+  skins.tint = Srgba::rgb(r, g, b);
+
+  if let Some(mut sprite) = player
+    .entity
+    .and_then(|entity| sprite_q.get_mut(entity).ok())
+  {
+    let image = asset_server.load(skin.texture.clone());
+    if sprite.image != image {
+      sprite.image = image;
+      sprite.custom_size = Some(Vec2::splat(SKIN_SIZE));
+    }
+  }
+
+  if let Some((mut text, mut transform)) = skins
+    .hat_entity
+    .and_then(|entity| hat_q.get_mut(entity).ok())
+  {
+    text.0 = skin.hat.clone().unwrap_or_default();
+    transform.translation = Vec3::new(
+      player.j,
+      player.i + player.size.y / 2.0 + HAT_FONT_SIZE / 2.0,
+      1.0,
+    );
+  }
+
+  if let Some((r, g, b)) = skin.trail {
+    if !player.grounded && tc - skins.trail_save >= TRAIL_EVERY_MS {
+      skins.trail_save = tc;
+      bursts.write(Burst {
+        kind: BurstKind::TRAIL(Color::srgb(r, g, b)),
+        i: player.i,
+        j: player.j,
+      });
+    }
+  }
+}
@@ -0,0 +1,299 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::bot::Bot;
+use crate::local::Local;
+use crate::locale::Locale;
+use crate::milestone::Trigger;
+use crate::net::Net;
+use crate::saves;
+use crate::{GameEvent, Player};
+
+const RECORD_PATH: &str = "saves/splits.record.ron";
+const LSS_PATH: &str = "saves/haskellHop.lss";
+
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+pub struct Split {
+  pub name: String,
+  pub trigger: Trigger,
+}
+
+#[derive(Asset, TypePath, Deserialize)]
+pub struct SplitTable {
+  pub category: String,
+  pub splits: Vec<Split>,
+}
+
+// times are seconds from the first jump, pb holds the cumulative split times of the best full run
+#[derive(Serialize, Deserialize, Default)]
+struct Record {
+  attempts: usize,
+  pb: Vec<f32>,
+  gold: Vec<Option<f32>>,
+}
+
+#[derive(Resource, Default)]
+pub struct Speedrun {
+  handle: Handle<SplitTable>,
+  record: Record,
+  started: Option<usize>,
+  time_vec: Vec<f32>,
+  gold_vec: Vec<bool>,
+  last_jump_count: usize,
+  elapsed: f32,
+}
+
+impl Speedrun {
+  fn segment(time_vec: &[f32], k: usize) -> f32 {
+    time_vec[k] - if k == 0 { 0.0 } else { time_vec[k - 1] }
+  }
+
+  // records a split at `elapsed`, and the run as the pb when it's the last of `splits` and the
+  // fastest yet. true once the run is over
+  fn split(&mut self, elapsed: f32, splits: usize) -> bool {
+    let k = self.time_vec.len();
+    self.time_vec.push(elapsed);
+
+    let segment = Speedrun::segment(&self.time_vec, k);
+    self.record.gold.resize(splits, None);
+    let gold = self.record.gold[k].is_none_or(|gold| segment < gold);
+    if gold {
+      self.record.gold[k] = Some(segment);
+    }
+    self.gold_vec.push(gold);
+
+    let finished = self.time_vec.len() == splits;
+    if finished {
+      self.started = None;
+      let pb = self.record.pb.last().copied();
+      if pb.is_none_or(|pb| elapsed < pb) {
+        self.record.pb = self.time_vec.clone();
+      }
+    }
+    finished
+  }
+
+  pub fn text(&self, tables: &Assets<SplitTable>, locale: &Locale) -> String {
+    let table = match tables.get(&self.handle) {
+      Some(table) => table,
+      None => return String::new(),
+    };
+    if self.started.is_none() && self.time_vec.is_empty() {
+      return String::new();
+    }
+
+    let mut lines = vec![format!("{:>16.2}", self.elapsed)];
+    for (k, split) in table.splits.iter().enumerate() {
+      let line = match self.time_vec.get(k) {
+        Some(time) => {
+          let delta = match self.record.pb.get(k) {
+            Some(pb) => format!("{:+.2}", time - pb),
+            None => "-".to_string(),
+          };
+          let gold = if self.gold_vec[k] { "*" } else { " " };
+          format!(
+            "{:<8}{:>8.2} {:>6}{}",
+            locale.tr(&split.name),
+            time,
+            delta,
+            gold
+          )
+        }
+        None => format!("{:<8}{:>8}", locale.tr(&split.name), "-"),
+      };
+      lines.push(line);
+    }
+    lines.join("\n")
+  }
+
+  fn lss(&self, table: &SplitTable) -> String {
+    let time = |seconds: f32| {
+      let ticks = (seconds as f64 * 1e7).round() as u64;
+      let s = ticks / 10_000_000;
+      format!(
+        "{:02}:{:02}:{:02}.{:07}",
+        s / 3600,
+        s / 60 % 60,
+        s % 60,
+        ticks % 10_000_000
+      )
+    };
+    let escape = |text: &str| {
+      text
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+    };
+
+    let segments = table
+      .splits
+      .iter()
+      .enumerate()
+      .map(|(k, split)| {
+        let pb = match self.record.pb.get(k) {
+          Some(pb) => format!("<RealTime>{}</RealTime>", time(*pb)),
+          None => String::new(),
+        };
+        let gold = match self.record.gold.get(k).copied().flatten() {
+          Some(gold) => format!("<RealTime>{}</RealTime>", time(gold)),
+          None => String::new(),
+        };
+        format!(
+          "    <Segment>\n      <Name>{}</Name>\n      <Icon />\n      <SplitTimes>\n        \
+           <SplitTime name=\"Personal Best\">{}</SplitTime>\n      </SplitTimes>\n      \
+           <BestSegmentTime>{}</BestSegmentTime>\n      <SegmentHistory />\n    </Segment>\n",
+          escape(&split.name),
+          pb,
+          gold
+        )
+      })
+      .collect::<String>();
+
+    format!(
+      "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<Run version=\"1.7.0\">\n  <GameIcon />\n  \
+       <GameName>haskellHop</GameName>\n  <CategoryName>{}</CategoryName>\n  \
+       <Offset>00:00:00</Offset>\n  <AttemptCount>{}</AttemptCount>\n  <AttemptHistory />\n  \
+       <Segments>\n{}  </Segments>\n  <AutoSplitterSettings />\n</Run>\n",
+      escape(&table.category),
+      self.record.attempts,
+      segments
+    )
+  }
+
+  fn export(&self, table: &SplitTable) {
+    if let Err(e) = saves::save_text(LSS_PATH, &self.lss(table)) {
+      warn!("couldn't export splits to {}: {}", LSS_PATH, e);
+    }
+  }
+}
+
+pub fn speedrun_setup(mut speedrun: ResMut<Speedrun>, asset_server: Res<AssetServer>) {
+  speedrun.handle = asset_server.load("data/default.splits.ron");
+  speedrun.record = saves::load(RECORD_PATH).unwrap_or_default();
+}
+
+// solo only, the timer starts on the first jump of a run and each split waits for the one before it
+pub fn speedrun_update(
+  time: Res<Time>,
+  keyboard_input: Res<ButtonInput<KeyCode>>,
+  player: Res<Player>,
+  local: Res<Local>,
+  net: Res<Net>,
+  bot: Res<Bot>,
+  tables: Res<Assets<SplitTable>>,
+  mut speedrun: ResMut<Speedrun>,
+  mut events: MessageReader<GameEvent>,
+) {
+  let events = events.read().copied().collect::<Vec<_>>();
+  let tc = time.elapsed().as_millis() as usize;
+  let last = std::mem::replace(&mut speedrun.last_jump_count, player.jump_count);
+  let reset = player.jump_count < last;
+
+  let table = match tables.get(&speedrun.handle) {
+    Some(table) => table,
+    None => return,
+  };
+
+  if keyboard_input.just_pressed(KeyCode::F5) {
+    speedrun.export(table);
+  }
+
+  if local.is_active() || net.is_online() || bot.active {
+    speedrun.started = None;
+    return;
+  }
+
+  if speedrun.started.is_none() && events.contains(&GameEvent::JUMP) {
+    speedrun.started = Some(tc);
+    speedrun.time_vec.clear();
+    speedrun.gold_vec.clear();
+    speedrun.record.attempts += 1;
+    saves::save(RECORD_PATH, &speedrun.record);
+  }
+
+  let started = match speedrun.started {
+    Some(started) => started,
+    None => return,
+  };
+  speedrun.elapsed = (tc - started) as f32 / 1000.0;
+
+  let k = speedrun.time_vec.len();
+  if let Some(split) = table.splits.get(k) {
+    if split.trigger.fired(last, player.jump_count, &events) {
+      let elapsed = speedrun.elapsed;
+      if speedrun.split(elapsed, table.splits.len()) {
+        speedrun.export(table);
+      }
+      saves::save(RECORD_PATH, &speedrun.record);
+    }
+  }
+
+  // deaths and restarts end the attempt, a finished run stays on screen until the next first jump
+  if reset && speedrun.started.is_some() {
+    speedrun.started = None;
+    speedrun.time_vec.clear();
+    speedrun.gold_vec.clear();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn attempt(speedrun: &mut Speedrun, times: &[f32]) {
+    speedrun.time_vec.clear();
+    speedrun.gold_vec.clear();
+    for (k, time) in times.iter().enumerate() {
+      assert_eq!(speedrun.split(*time, 2), k == 1);
+    }
+  }
+
+  #[test]
+  fn gold_and_pb() {
+    let mut speedrun = Speedrun::default();
+    attempt(&mut speedrun, &[1.0, 3.0]);
+    assert_eq!(speedrun.gold_vec, [true, true]);
+    assert_eq!(speedrun.record.pb, [1.0, 3.0]);
+
+    // slower first split, faster second segment and a faster run
+    attempt(&mut speedrun, &[1.5, 2.5]);
+    assert_eq!(speedrun.gold_vec, [false, true]);
+    assert_eq!(speedrun.record.gold, [Some(1.0), Some(1.0)]);
+    assert_eq!(speedrun.record.pb, [1.5, 2.5]);
+
+    // a gold first split in a slower run leaves the pb alone
+    attempt(&mut speedrun, &[0.5, 4.0]);
+    assert_eq!(speedrun.gold_vec, [true, false]);
+    assert_eq!(speedrun.record.gold, [Some(0.5), Some(1.0)]);
+    assert_eq!(speedrun.record.pb, [1.5, 2.5]);
+  }
+
+  #[test]
+  fn lss_times_and_escapes() {
+    let table = SplitTable {
+      category: "any% <no java>".to_string(),
+      splits: vec![Split {
+        name: "r&d".to_string(),
+        trigger: Trigger::Jump(1),
+      }],
+    };
+    let speedrun = Speedrun {
+      record: Record {
+        attempts: 3,
+        pb: vec![3725.5],
+        gold: vec![Some(61.25)],
+      },
+      ..default()
+    };
+    let lss = speedrun.lss(&table);
+    assert!(lss.contains("<CategoryName>any% &lt;no java&gt;</CategoryName>"));
+    assert!(lss.contains("<Name>r&amp;d</Name>"));
+    assert!(lss.contains("<AttemptCount>3</AttemptCount>"));
+    assert!(lss.contains(
+      "<SplitTime name=\"Personal Best\"><RealTime>01:02:05.5000000</RealTime></SplitTime>"
+    ));
+    assert!(
+      lss.contains("<BestSegmentTime><RealTime>00:01:01.2500000</RealTime></BestSegmentTime>")
+    );
+  }
+}
@@ -0,0 +1,150 @@
+use bevy::prelude::*;
+use rand::{thread_rng, Rng};
+
+use crate::access::Access;
+use crate::glyphs;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TextEffect {
+  // bevy can't offset single glyphs, so the wave ripples each character's size instead
+  WAVE { amplitude: f32, speed: f32 },
+  SHAKE(f32),
+  TYPEWRITER { ms_per_char: usize },
+  SCRAMBLE { ms: usize },
+  POP { scale: f32, ms: usize },
+}
+
+// one span per character under the text entity, so colour and size can differ per character;
+// the entity's own TextFont and TextColor are the base style and its own text stays empty
+#[derive(Component, Default)]
+pub struct TextFx {
+  pub effect_vec: Vec<TextEffect>,
+  pub palette: Vec<Color>,
+  // world position to shake around, none for ui text that the layout places
+  pub anchor: Option<Vec3>,
+  value: String,
+  time_save: usize,
+}
+
+impl TextFx {
+  pub fn new(effect_vec: Vec<TextEffect>) -> Self {
+    TextFx {
+      effect_vec,
+      ..default()
+    }
+  }
+
+  // restarts the reveal, scramble and pop whenever the string actually changes
+  pub fn set(&mut self, tc: usize, value: impl Into<String>) {
+    let value = value.into();
+    if value != self.value {
+      self.value = value;
+      self.time_save = tc;
+    }
+  }
+}
+
+type FxText<'a> = (
+  Entity,
+  &'a TextFont,
+  &'a TextColor,
+  Option<&'a Children>,
+  Option<&'a mut Transform>,
+  &'a TextFx,
+);
+
+pub fn text_fx_update(
+  time: Res<Time>,
+  access: Res<Access>,
+  mut text_q: Query<FxText>,
+  mut span_q: Query<(&mut TextSpan, &mut TextFont, &mut TextColor), Without<TextFx>>,
+  mut commands: Commands,
+) {
+  let mut rng = thread_rng();
+  let tc = time.elapsed().as_millis() as usize;
+  let t = time.elapsed_secs();
+
+  for (entity, font, colour, children, transform, fx) in &mut text_q {
+    let age = tc - fx.time_save;
+
+    let mut shown = fx.value.chars().count();
+    let mut resolved = shown;
+    let mut wave = (0.0, 0.0);
+    let mut shake = 0.0;
+    let mut scale = 1.0;
+
+    for effect in &fx.effect_vec {
+      match *effect {
+        // reduced motion keeps the text still, reduced flashing keeps the characters steady
+        TextEffect::WAVE { .. } | TextEffect::SHAKE(_) | TextEffect::POP { .. }
+          if access.reduced_motion => {}
+        TextEffect::SCRAMBLE { .. } if access.reduce_flashing => {}
+        TextEffect::WAVE { amplitude, speed } => wave = (amplitude, speed),
+        TextEffect::SHAKE(px) => shake = px,
+        TextEffect::TYPEWRITER { ms_per_char } => shown = shown.min(age / ms_per_char.max(1)),
+        TextEffect::SCRAMBLE { ms } => {
+          resolved = (resolved as f32 * (age as f32 / ms.max(1) as f32)) as usize;
+        }
+        TextEffect::POP { scale: pop, ms } if age < ms => {
+          scale = 1.0 + (pop - 1.0) * (1.0 - age as f32 / ms as f32);
+        }
+        _ => {}
+      }
+    }
+
+    let span_vec = children.map_or(vec![], |children| {
+      children
+        .iter()
+        .filter(|child| span_q.contains(*child))
+        .collect::<Vec<_>>()
+    });
+    let alpha = colour.0.alpha();
+
+    for (k, c) in fx.value.chars().take(shown).enumerate() {
+      let value = if k < resolved {
+        c.to_string()
+      } else {
+        glyphs(1, &mut rng)
+      };
+      // whole sizes only, every size gets its own glyph atlas
+      let span_font = TextFont {
+        font_size: (font.font_size * (1.0 + wave.0 * (t * wave.1 - k as f32 * 0.6).sin())).round(),
+        ..font.clone()
+      };
+      let span_colour = access.palette.apply(match fx.palette.len() {
+        0 => colour.0,
+        n => fx.palette[k % n].with_alpha(alpha),
+      });
+
+      match span_vec.get(k) {
+        Some(&span) => {
+          if let Ok((mut text, mut text_font, mut text_colour)) = span_q.get_mut(span) {
+            text.0 = value;
+            *text_font = span_font;
+            text_colour.0 = span_colour;
+          }
+        }
+        None => {
+          commands
+            .entity(entity)
+            .with_child((TextSpan(value), span_font, TextColor(span_colour)));
+        }
+      }
+    }
+    for &span in span_vec.iter().skip(shown) {
+      commands.entity(span).despawn();
+    }
+
+    if let Some(mut transform) = transform {
+      transform.scale = Vec3::splat(scale * access.text_scale);
+      if let Some(anchor) = fx.anchor {
+        transform.translation = anchor +
+          Vec3::new(
+            rng.gen_range(-1.0..=1.0) * shake,
+            rng.gen_range(-1.0..=1.0) * shake,
+            0.0,
+          );
+      }
+    }
+  }
+}
@@ -0,0 +1,186 @@
+use bevy::prelude::*;
+
+use crate::access::{Access, Fades, SLOW_FADE};
+use crate::net::Clock;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Ease {
+  LINEAR,
+  IN_QUAD,
+  OUT_QUAD,
+  IN_OUT_QUAD,
+  OUT_BACK,
+}
+
+impl Ease {
+  pub fn apply(self, t: f32) -> f32 {
+    match self {
+      Ease::LINEAR => t,
+      Ease::IN_QUAD => t * t,
+      Ease::OUT_QUAD => t * (2.0 - t),
+      Ease::IN_OUT_QUAD => {
+        if t < 0.5 {
+          2.0 * t * t
+        } else {
+          -1.0 + (4.0 - 2.0 * t) * t
+        }
+      }
+      Ease::OUT_BACK => {
+        let c1 = 1.70158;
+        1.0 + (c1 + 1.0) * (t - 1.0).powi(3) + c1 * (t - 1.0).powi(2)
+      }
+    }
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TweenTarget {
+  CLEAR_COLOUR(Color, Color),
+  SPRITE_COLOUR(Color, Color),
+  TEXT_COLOUR(Color, Color),
+  TRANSLATION(Vec3, Vec3),
+  SCALE(Vec3, Vec3),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TweenTag {
+  BACKGROUND_FADE,
+  SCORE_FADE_OUT,
+  JAVA_FADE_IN,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Tween {
+  pub entity: Option<Entity>,
+  pub target: TweenTarget,
+  pub ease: Ease,
+  pub duration_ms: usize,
+  pub tag: Option<TweenTag>,
+}
+
+#[derive(Message)]
+pub struct TweenDone {
+  pub tag: TweenTag,
+}
+
+#[derive(Resource, Default)]
+pub struct Tweens {
+  // the flag is whether TweenDone went out already, a slowed fade keeps drawing after it has
+  tween_vec: Vec<(Tween, usize, bool)>,
+}
+
+impl Tweens {
+  // a tagged tween replaces whatever was running under the same tag
+  pub fn start(&mut self, tc: usize, tween: Tween) {
+    if let Some(tag) = tween.tag {
+      self.cancel(tag);
+    }
+    self.tween_vec.push((tween, tc, false));
+  }
+
+  pub fn clear(&mut self) {
+    self.tween_vec.clear();
+  }
+
+  pub fn cancel(&mut self, tag: TweenTag) {
+    self
+      .tween_vec
+      .retain(|(tween, _, _)| tween.tag != Some(tag));
+  }
+}
+
+pub fn lerp_colour(from: Color, to: Color, k: f32) -> Color {
+  let (from, to) = (from.to_srgba(), to.to_srgba());
+  Color::srgba(
+    from.red + (to.red - from.red) * k,
+    from.green + (to.green - from.green) * k,
+    from.blue + (to.blue - from.blue) * k,
+    from.alpha + (to.alpha - from.alpha) * k,
+  )
+}
+
+pub fn tween_update(
+  clock: Clock,
+  access: Res<Access>,
+  mut tweens: ResMut<Tweens>,
+  mut background_colour: ResMut<ClearColor>,
+  mut transform_q: Query<&mut Transform>,
+  mut sprite_q: Query<&mut Sprite>,
+  mut text_q: Query<&mut TextColor>,
+  mut done: MessageWriter<TweenDone>,
+) {
+  let tc = clock.now();
+
+  tweens.tween_vec.retain_mut(|(tween, time_save, sent)| {
+    let t = ((tc - *time_save) as f32 / tween.duration_ms.max(1) as f32).min(1.0);
+    let k = tween.ease.apply(t);
+
+    // the fade setting only changes how the background looks, TweenDone keeps the
+    // tween's own schedule since the phases wait on it
+    let fade = match access.fades {
+      Fades::NORMAL => t,
+      Fades::SLOW => {
+        ((tc - *time_save) as f32 / (tween.duration_ms as f32 * SLOW_FADE).max(1.0)).min(1.0)
+      }
+      Fades::OFF => 1.0,
+    };
+
+    let alive = match (tween.target, tween.entity) {
+      (TweenTarget::CLEAR_COLOUR(from, to), _) => {
+        *background_colour = ClearColor(lerp_colour(from, to, tween.ease.apply(fade)));
+        true
+      }
+      (TweenTarget::SPRITE_COLOUR(from, to), Some(entity)) => sprite_q
+        .get_mut(entity)
+        .map(|mut sprite| sprite.color = lerp_colour(from, to, k))
+        .is_ok(),
+      (TweenTarget::TEXT_COLOUR(from, to), Some(entity)) => text_q
+        .get_mut(entity)
+        .map(|mut colour| colour.0 = lerp_colour(from, to, k))
+        .is_ok(),
+      (TweenTarget::TRANSLATION(from, to), Some(entity)) => transform_q
+        .get_mut(entity)
+        .map(|mut transform| transform.translation = from.lerp(to, k))
+        .is_ok(),
+      (TweenTarget::SCALE(from, to), Some(entity)) => transform_q
+        .get_mut(entity)
+        .map(|mut transform| transform.scale = from.lerp(to, k))
+        .is_ok(),
+      (_, None) => false,
+    };
+
+    if alive && t >= 1.0 && !*sent {
+      *sent = true;
+      if let Some(tag) = tween.tag {
+        done.write(TweenDone { tag });
+      }
+    }
+    let drawing = matches!(tween.target, TweenTarget::CLEAR_COLOUR(..)) && fade < 1.0;
+    alive && (t < 1.0 || drawing)
+  });
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn eases_run_from_zero_to_one() {
+    for ease in [
+      Ease::LINEAR,
+      Ease::IN_QUAD,
+      Ease::OUT_QUAD,
+      Ease::IN_OUT_QUAD,
+      Ease::OUT_BACK,
+    ] {
+      assert!(ease.apply(0.0).abs() < 1e-6, "{:?} at 0", ease);
+      assert!((ease.apply(1.0) - 1.0).abs() < 1e-6, "{:?} at 1", ease);
+    }
+    assert_eq!(Ease::LINEAR.apply(0.5), 0.5);
+    assert_eq!(Ease::IN_QUAD.apply(0.5), 0.25);
+    assert_eq!(Ease::OUT_QUAD.apply(0.5), 0.75);
+    assert_eq!(Ease::IN_OUT_QUAD.apply(0.5), 0.5);
+    // overshoots on the way in
+    assert!(Ease::OUT_BACK.apply(0.5) > 1.0);
+  }
+}